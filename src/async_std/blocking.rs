@@ -0,0 +1,71 @@
+//! Run blocking operations on a shared thread pool
+
+use std::sync::OnceLock;
+
+use super::*;
+use crate::closure::FnCallOnce;
+use crate::pointer::*;
+use crate::runtime::{catch_unwind_safe, join};
+use crate::threadpool::*;
+
+fn blocking_pool() -> Result<&'static ThreadPool> {
+	static POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+	if let Some(pool) = POOL.get() {
+		return Ok(pool);
+	}
+
+	let pool = ThreadPool::new_blocking_signals(ThreadPool::default_count()?)?;
+
+	Ok(POOL.get_or_init(|| pool))
+}
+
+/// Run the blocking function `func` on a shared thread pool, suspending until
+/// it completes
+///
+/// If the current task is interrupted while `func` is running, the thread
+/// running it is interrupted, causing blocking system calls to fail with
+/// `EINTR`. `func` may check [`TaskContext::cancelled`] to detect this.
+///
+/// Returns an [`Interrupted`] error if the task was interrupted before `func`
+/// started
+///
+/// The pool has twice as many threads as there are online cpus, and is shared
+/// with every other blocking operation. Each call holds a thread until `func`
+/// returns, so many long running calls delay the rest, and can stall the pool
+/// entirely if they are waiting on calls that haven't been given a thread yet
+///
+/// # Panics
+/// If `func` panics, the panic is resumed on the calling task
+///
+/// [`Interrupted`]: ErrorKind::Interrupted
+#[asynchronous]
+pub async fn run_blocking<F, Output>(func: F) -> Result<Output>
+where
+	F: FnOnce(&TaskContext) -> Output + Send,
+	Output: Send
+{
+	let pool = blocking_pool()?;
+	let mut output = None;
+
+	let submitted = {
+		let mut call = FnCallOnce::new(|context: &'static TaskContext| {
+			output = Some(catch_unwind_safe(|| func(context)));
+		});
+
+		/* Safety: the closure never unwinds, and we check the return value */
+		let mut work = unsafe { Work::new(call.as_dyn()) };
+
+		/* Safety: the work lives until the future completes */
+		block_on_thread_safe(unsafe { pool.submit(ptr!(&mut work)) }).await
+	};
+
+	if !submitted {
+		return Err(interrupt_error().await);
+	}
+
+	#[allow(clippy::expect_used)]
+	let output = output.expect("Blocking function did not run");
+
+	Ok(join(output))
+}
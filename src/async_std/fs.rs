@@ -0,0 +1,269 @@
+//! Async filesystem utilities
+//!
+//! [`Watcher`] reports changes to files and directories, backed by inotify.
+//! The inotify fd is polled for readiness, so waiting for events does not
+//! occupy a [`run_blocking`] thread.
+//!
+//! [`run_blocking`]: super::blocking::run_blocking
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use enumflags2::{make_bitflags, BitFlags};
+
+use super::io::wait_ready;
+use super::*;
+use crate::os::error::OsError;
+use crate::os::inotify::*;
+use crate::os::poll::PollFlag;
+use crate::os::{unistd, with_path_as_cstr};
+
+/// The events watched by [`Watcher::add`]
+pub const DEFAULT_WATCH_MASK: BitFlags<WatchFlag> = make_bitflags!(WatchFlag::{
+	Create | CloseWrite | Modify | Delete | MovedFrom | MovedTo | DeleteSelf | MoveSelf
+});
+
+const EVENT_BUFFER_SIZE: usize = 16 * MIN_EVENT_BUFFER_SIZE;
+
+/// A change reported by a [`Watcher`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+	/// A file or directory was created
+	Create(PathBuf),
+
+	/// A file was modified
+	Modify(PathBuf),
+
+	/// A file or directory was deleted. Also reported when a watched path
+	/// itself is deleted
+	Delete(PathBuf),
+
+	/// A file or directory was renamed within the watched directories
+	Rename { from: PathBuf, to: PathBuf },
+
+	/// A file or directory was moved out of the watched directories
+	MovedOut(PathBuf),
+
+	/// A file or directory was moved into the watched directories
+	MovedIn(PathBuf),
+
+	/// The watch on a path was removed, either by [`Watcher::remove`] or
+	/// because the path was deleted or unmounted
+	Removed(PathBuf),
+
+	/// The kernel event queue overflowed, and events were lost
+	Overflow
+}
+
+/// Watches paths for changes, yielding [`WatchEvent`]s
+///
+/// # Examples
+///
+/// ```
+/// let mut watcher = Watcher::new()?;
+///
+/// watcher.add("/tmp")?;
+///
+/// while let Some(event) = watcher.next().await {
+/// 	println!("{:?}", event?);
+/// }
+/// ```
+pub struct Watcher {
+	fd: OwnedFd,
+
+	/* several paths may resolve to the same inode, and share a watch */
+	watches: HashMap<i32, Vec<PathBuf>>,
+	pending: VecDeque<WatchEvent>,
+
+	/* a move whose pair has not been read yet */
+	moved_from: Option<(u32, PathBuf)>
+}
+
+#[asynchronous]
+impl Watcher {
+	/// Create a watcher with no watched paths
+	pub fn new() -> Result<Self> {
+		let fd = inotify_init1(InitFlag::CloseOnExec | InitFlag::NonBlock)?;
+
+		Ok(Self {
+			fd,
+			watches: HashMap::new(),
+			pending: VecDeque::new(),
+			moved_from: None
+		})
+	}
+
+	/// Watch `path` for the events in [`DEFAULT_WATCH_MASK`]
+	///
+	/// If `path` is a directory, changes to its direct children are reported
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn add(&mut self, path: impl AsRef<Path>) -> Result<()> {
+		self.add_mask(path, DEFAULT_WATCH_MASK)
+	}
+
+	/// Watch `path` for the events in `mask`. Replaces the mask if `path` is
+	/// already watched, unless `mask` contains [`WatchFlag::MaskAdd`]
+	///
+	/// Paths that resolve to the same file share a single watch. Its events
+	/// are reported under the path that was added first
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn add_mask(&mut self, path: impl AsRef<Path>, mask: BitFlags<WatchFlag>) -> Result<()> {
+		let path = path.as_ref();
+		let fd = self.fd.as_fd();
+		let wd = with_path_as_cstr(path, |path| Ok(inotify_add_watch(fd, path, mask)?))?;
+
+		let paths = self.watches.entry(wd).or_default();
+
+		if !paths.iter().any(|watched| watched == path) {
+			paths.push(path.to_path_buf());
+		}
+
+		Ok(())
+	}
+
+	/// Stop watching `path`
+	///
+	/// The watch itself is only removed once none of the paths sharing it are
+	/// watched, which is then reported as [`WatchEvent::Removed`]
+	///
+	/// Returns a [`NotFound`] error if `path` is not watched
+	///
+	/// [`NotFound`]: ErrorKind::NotFound
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn remove(&mut self, path: impl AsRef<Path>) -> Result<()> {
+		let path = path.as_ref();
+		let (wd, paths) = self
+			.watches
+			.iter_mut()
+			.find(|(_, paths)| paths.iter().any(|watched| watched == path))
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+
+		if paths.len() == 1 {
+			inotify_rm_watch(self.fd.as_fd(), *wd)?;
+		} else {
+			paths.retain(|watched| watched != path);
+		}
+
+		Ok(())
+	}
+
+	/// The paths currently watched
+	pub fn paths(&self) -> impl Iterator<Item = &Path> {
+		self.watches.values().flatten().map(PathBuf::as_path)
+	}
+
+	/// Wait for the next event
+	///
+	/// # Cancel safety
+	///
+	/// This function is cancel safe. Events already read are kept until the
+	/// next call.
+	pub async fn recv(&mut self) -> Result<WatchEvent> {
+		loop {
+			if let Some(event) = self.pending.pop_front() {
+				return Ok(event);
+			}
+
+			let mut buf = vec![0u8; EVENT_BUFFER_SIZE];
+			let fd = self.fd.as_fd();
+
+			match unistd::read(fd, (&mut buf[..]).into()) {
+				Ok(read) => self.parse(&buf[..read]),
+
+				/* both halves of a rename are queued before it returns, so a
+				 * move without its pair in the queue left the watched
+				 * directories */
+				Err(OsError::Again) => match self.moved_from.take() {
+					Some((_, from)) => self.pending.push_back(WatchEvent::MovedOut(from)),
+					None => {
+						wait_ready(fd, PollFlag::In.into()).await?;
+					}
+				},
+
+				Err(err) => return Err(err.into())
+			}
+		}
+	}
+
+	fn path_of(&self, event: &Event<'_>) -> Option<PathBuf> {
+		let watched = self.watches.get(&event.wd)?.first()?;
+
+		Some(match event.name {
+			Some(name) => watched.join(OsStr::from_bytes(name.to_bytes())),
+			None => watched.clone()
+		})
+	}
+
+	fn parse(&mut self, buf: &[u8]) {
+		for event in Events::new(buf) {
+			let mask = event.mask;
+
+			if mask.contains(WatchFlag::QueueOverflow) {
+				self.pending.push_back(WatchEvent::Overflow);
+
+				continue;
+			}
+
+			let Some(path) = self.path_of(&event) else {
+				continue;
+			};
+
+			/* a move that isn't immediately followed by its pair left the
+			 * watched directories. the pair may be in the next read */
+			if let Some((cookie, from)) = self.moved_from.take() {
+				if mask.contains(WatchFlag::MovedTo) && event.cookie == cookie {
+					self.pending
+						.push_back(WatchEvent::Rename { from, to: path });
+
+					continue;
+				}
+
+				self.pending.push_back(WatchEvent::MovedOut(from));
+			}
+
+			if mask.contains(WatchFlag::MovedFrom) {
+				self.moved_from = Some((event.cookie, path));
+			} else if mask.contains(WatchFlag::MovedTo) {
+				self.pending.push_back(WatchEvent::MovedIn(path));
+			} else if mask.contains(WatchFlag::Create) {
+				self.pending.push_back(WatchEvent::Create(path));
+			} else if mask.intersects(WatchFlag::Modify | WatchFlag::CloseWrite) {
+				/* a write followed by close reports the same change twice */
+				if self.pending.back() != Some(&WatchEvent::Modify(path.clone())) {
+					self.pending.push_back(WatchEvent::Modify(path));
+				}
+			} else if mask.intersects(WatchFlag::Delete | WatchFlag::DeleteSelf) {
+				self.pending.push_back(WatchEvent::Delete(path));
+			} else if mask.contains(WatchFlag::Ignored) {
+				let paths = self.watches.remove(&event.wd).unwrap_or_default();
+
+				self.pending
+					.extend(paths.into_iter().map(WatchEvent::Removed));
+			}
+		}
+	}
+}
+
+#[asynchronous]
+impl AsyncIterator for Watcher {
+	type Item = Result<WatchEvent>;
+
+	async fn next(&mut self) -> Option<Result<WatchEvent>> {
+		Some(self.recv().await)
+	}
+}
+
+impl AsFd for Watcher {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl AsRawFd for Watcher {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
@@ -114,6 +114,8 @@ macro_rules! impl_bufread {
 		impl< $($lt,)? R: Read + ?Sized> Read for $type< $($lt,)? R> {
 			async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
 				if !self.buffer().is_empty() {
+					consume_budget().await;
+
 					return Ok(self.read_into(buf));
 				}
 
@@ -166,6 +166,8 @@ impl<W: Write + ?Sized> BufWriter<W> {
 impl<W: Write + ?Sized> Write for BufWriter<W> {
 	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
 		if self.spare_capacity() > 0 {
+			consume_budget().await;
+
 			return Ok(self.write_buffered(buf));
 		}
 
@@ -0,0 +1,186 @@
+//! Copying between readers and writers
+//!
+//! When both ends expose a file descriptor through [`Read::copy_fd`] and
+//! [`Write::copy_fd`], data is moved within the kernel using
+//! `copy_file_range`, `sendfile` or `splice`, whichever the pair of
+//! descriptors supports. Transfers are performed on the [`run_blocking`]
+//! thread pool. A transfer that would block on a nonblocking pipe waits for
+//! it to become ready instead.
+//!
+//! [`run_blocking`]: crate::async_std::blocking::run_blocking
+
+use super::pipe::wait_transfer;
+use super::*;
+use crate::async_std::blocking::run_blocking;
+use crate::os::error::OsError;
+use crate::os::splice::*;
+
+/// The maximum amount transferred by a single system call
+const MAX_TRANSFER: usize = 0x4000_0000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Method {
+	CopyFileRange,
+	SendFile,
+	Splice
+}
+
+impl Method {
+	const ALL: [Self; 3] = [Self::CopyFileRange, Self::SendFile, Self::Splice];
+
+	fn transfer(self, fd_in: BorrowedFd<'_>, fd_out: BorrowedFd<'_>) -> OsResult<usize> {
+		match self {
+			Self::CopyFileRange => copy_file_range(fd_in, None, fd_out, None, MAX_TRANSFER, 0),
+			Self::SendFile => sendfile(fd_out, fd_in, None, MAX_TRANSFER),
+			Self::Splice => splice(
+				fd_in,
+				None,
+				fd_out,
+				None,
+				MAX_TRANSFER,
+				SpliceFlag::Move | SpliceFlag::More
+			)
+		}
+	}
+}
+
+/// Errors indicating that the pair of descriptors is not supported by a
+/// method, before any data was transferred
+const fn is_unsupported(err: OsError) -> bool {
+	matches!(
+		err,
+		OsError::Inval | OsError::XDev | OsError::NoSys | OsError::OpNotSupp | OsError::BadF
+	)
+}
+
+/// Transfer everything from `fd_in` to `fd_out` within the kernel
+///
+/// Returns `None` if no method supports the descriptors, or none of them
+/// transferred any data. Once a method transfers data, errors from it are
+/// returned as is.
+#[asynchronous]
+async fn copy_fds(fd_in: BorrowedFd<'_>, fd_out: BorrowedFd<'_>) -> Result<Option<u64>> {
+	let mut methods = Method::ALL.iter().copied();
+	let mut method = methods.next();
+	let mut total = 0u64;
+
+	while let Some(current) = method {
+		let result = match run_blocking(|_| current.transfer(fd_in, fd_out)).await {
+			Ok(result) => result,
+			Err(err) if err.is_interrupted() && total != 0 => break,
+			Err(err) => return Err(err)
+		};
+
+		match result {
+			/* files in procfs and sysfs report a size of zero, so these
+			 * methods see them as empty. try the next one */
+			Ok(0) if total == 0 => method = methods.next(),
+			Ok(0) => break,
+
+			#[allow(clippy::arithmetic_side_effects)]
+			Ok(n) => total += n as u64,
+
+			Err(err) if total == 0 && is_unsupported(err) => method = methods.next(),
+			Err(OsError::Again) => match wait_transfer(fd_in, fd_out).await {
+				Ok(()) => (),
+				Err(err) if err.is_interrupted() && total != 0 => break,
+				Err(err) => return Err(err)
+			},
+			Err(OsError::Intr) if total != 0 => break,
+			Err(err) => return Err(err.into())
+		}
+	}
+
+	Ok(method.map(|_| total))
+}
+
+/// Copy the entire contents of `reader` into `writer`, returning the number
+/// of bytes copied
+///
+/// If both ends are backed by file descriptors, data is moved without copying
+/// through user space. Otherwise, the data is copied through a buffer of
+/// [`DEFAULT_BUFFER_SIZE`] bytes
+///
+/// See also [`std::io::copy`]
+///
+/// # Cancel safety
+///
+/// This function is not cancel safe. Data read but not yet written is lost
+/// on interrupt, since an error is returned.
+#[asynchronous]
+pub async fn copy<R, W>(reader: &mut R, writer: &mut W) -> Result<u64>
+where
+	R: Read + ?Sized,
+	W: Write + ?Sized
+{
+	if let (Some(fd_in), Some(fd_out)) = (reader.copy_fd(), writer.copy_fd()) {
+		if let Some(copied) = copy_fds(fd_in, fd_out).await? {
+			return Ok(copied);
+		}
+	}
+
+	let mut buf = vec![0; DEFAULT_BUFFER_SIZE];
+	let mut total = 0u64;
+
+	loop {
+		let read = match reader.read(&mut buf).await {
+			Ok(0) => break,
+			Ok(n) => n,
+			Err(err) if err.is_interrupted() && total != 0 => break,
+			Err(err) => return Err(err)
+		};
+
+		let wrote = writer.try_write_all(&buf[..read]).await?;
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(total += wrote as u64);
+
+		if unlikely(wrote < read) {
+			return Err(short_io_error_unless_interrupt().await);
+		}
+	}
+
+	Ok(total)
+}
+
+/// Copy the entire contents of the buffered `reader` into `writer`, writing
+/// directly from the reader's buffer. Returns the number of bytes copied
+///
+/// # Cancel safety
+///
+/// This function is cancel safe. Data not yet written remains in the
+/// reader's buffer. Once the interrupt is cleared, call this function again
+/// to resume copying.
+#[asynchronous]
+pub async fn copy_buf<R, W>(reader: &mut R, writer: &mut W) -> Result<u64>
+where
+	R: BufRead + ?Sized,
+	W: Write + ?Sized
+{
+	let mut total = 0u64;
+
+	loop {
+		if reader.buffer().is_empty() {
+			match reader.fill().await {
+				Ok(0) => break,
+				Ok(_) => (),
+				Err(err) if err.is_interrupted() && total != 0 => break,
+				Err(err) => return Err(err)
+			}
+		}
+
+		let wrote = match writer.write(reader.buffer()).await {
+			Ok(0) => return Err(short_io_error_unless_interrupt().await),
+			Ok(n) => n,
+			Err(err) if err.is_interrupted() && total != 0 => break,
+			Err(err) => return Err(err)
+		};
+
+		reader.consume(wrote);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(total += wrote as u64);
+	}
+
+	Ok(total)
+}
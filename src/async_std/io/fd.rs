@@ -0,0 +1,109 @@
+//! Async I/O on raw file descriptors
+
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+
+use super::*;
+use crate::async_std::blocking::run_blocking;
+use crate::os::unistd;
+
+/// Read from `fd` on the [`run_blocking`] thread pool
+#[asynchronous]
+pub async fn read_fd(fd: BorrowedFd<'_>, buf: &mut [u8]) -> Result<usize> {
+	read_into!(buf);
+
+	let read = run_blocking(|_| unistd::read(fd, (&mut *buf).into())).await??;
+
+	check_interrupt_if_zero(length_check(buf, read)).await
+}
+
+/// Write to `fd` on the [`run_blocking`] thread pool
+#[asynchronous]
+pub async fn write_fd(fd: BorrowedFd<'_>, buf: &[u8]) -> Result<usize> {
+	write_from!(buf);
+
+	let wrote = run_blocking(|_| unistd::write(fd, buf.into())).await??;
+
+	check_interrupt_if_zero(length_check(buf, wrote)).await
+}
+
+/// A file descriptor with blocking reads and writes, performed on the
+/// [`run_blocking`] thread pool
+///
+/// Used for descriptors that cannot be polled, such as regular files, which
+/// are always reported ready
+#[derive(Debug)]
+pub struct AsyncFd {
+	fd: OwnedFd
+}
+
+impl AsyncFd {
+	#[must_use]
+	pub const fn new(fd: OwnedFd) -> Self {
+		Self { fd }
+	}
+
+	#[must_use]
+	pub fn into_inner(self) -> OwnedFd {
+		self.fd
+	}
+}
+
+#[asynchronous]
+impl Read for AsyncFd {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_fd(self.fd.as_fd(), buf).await
+	}
+
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		Some(self.fd.as_fd())
+	}
+}
+
+#[asynchronous]
+impl Write for AsyncFd {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		write_fd(self.fd.as_fd(), buf).await
+	}
+
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		Some(self.fd.as_fd())
+	}
+}
+
+impl AsFd for AsyncFd {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl AsRawFd for AsyncFd {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl IntoRawFd for AsyncFd {
+	fn into_raw_fd(self) -> RawFd {
+		self.fd.into_raw_fd()
+	}
+}
+
+#[allow(unsafe_code)]
+impl FromRawFd for AsyncFd {
+	unsafe fn from_raw_fd(fd: RawFd) -> Self {
+		/* Safety: guaranteed by caller */
+		Self::new(unsafe { OwnedFd::from_raw_fd(fd) })
+	}
+}
+
+impl From<OwnedFd> for AsyncFd {
+	fn from(fd: OwnedFd) -> Self {
+		Self::new(fd)
+	}
+}
+
+impl From<AsyncFd> for OwnedFd {
+	fn from(fd: AsyncFd) -> Self {
+		fd.fd
+	}
+}
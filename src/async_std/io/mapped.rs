@@ -0,0 +1,326 @@
+//! Reading files through memory maps
+//!
+//! A [`MappedFile`] serves reads directly from a private, read only mapping of
+//! the file. No system calls are made on the hot path, except when the
+//! position moves outside the current window and a new one is mapped.
+//!
+//! The file must not be truncated while it is mapped, otherwise accessing the
+//! truncated pages raises `SIGBUS`
+
+use std::fs::File;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::slice;
+
+use enumflags2::BitFlags;
+
+use super::*;
+use crate::os::mman::*;
+
+/// The default amount of the file mapped at a time (1 GiB)
+pub const DEFAULT_WINDOW_SIZE: usize = 0x4000_0000;
+
+/// Access pattern hints applied to each mapped window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapHint {
+	/// Pages are read in order, read ahead aggressively and free them soon
+	/// after they are read
+	Sequential,
+
+	/// Pages are read in random order, don't read ahead
+	Random,
+
+	/// Read the window in ahead of time
+	WillNeed,
+
+	/// Back the window with huge pages, if the filesystem supports it
+	HugePage
+}
+
+impl From<MapHint> for Advice {
+	fn from(hint: MapHint) -> Self {
+		match hint {
+			MapHint::Sequential => Self::Sequential,
+			MapHint::Random => Self::Random,
+			MapHint::WillNeed => Self::WillNeed,
+			MapHint::HugePage => Self::HugePage
+		}
+	}
+}
+
+/// A file reader backed by a memory map, implementing [`BufRead`] and
+/// [`Seek`]
+///
+/// Files larger than the window size are mapped one window at a time. The
+/// length of the file is read once when it is opened.
+///
+/// # Examples
+///
+/// ```
+/// let file = MappedFile::open("access.log")?.hint(MapHint::Sequential);
+/// let mut lines = file.lines();
+///
+/// while let Some(line) = lines.next().await {
+/// 	parse(&line?);
+/// }
+/// ```
+pub struct MappedFile {
+	fd: OwnedFd,
+	len: u64,
+	pos: u64,
+	window: Map<'static>,
+	window_start: u64,
+	window_size: usize,
+	hint: Option<MapHint>
+}
+
+#[asynchronous]
+impl MappedFile {
+	/// Open the file at `path` for reading
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+		Self::new(File::open(path)?.into())
+	}
+
+	/// Create a reader for the open file `fd`, starting at the beginning of
+	/// the file
+	pub fn new(fd: OwnedFd) -> Result<Self> {
+		Self::with_window_size(fd, DEFAULT_WINDOW_SIZE)
+	}
+
+	/// Create a reader that maps at most `window_size` bytes of the file at a
+	/// time
+	///
+	/// # Panics
+	/// If `window_size` is zero
+	pub fn with_window_size(fd: OwnedFd, window_size: usize) -> Result<Self> {
+		assert!(window_size > 0);
+
+		let file = File::from(fd);
+		let len = file.metadata()?.len();
+
+		Ok(Self {
+			fd: file.into(),
+			len,
+			pos: 0,
+			window: Map::new(),
+			window_start: 0,
+			window_size,
+			hint: None
+		})
+	}
+
+	/// Apply `hint` to every window mapped from now on, replacing any
+	/// previous hint
+	#[must_use]
+	pub const fn hint(mut self, hint: MapHint) -> Self {
+		self.hint = Some(hint);
+		self
+	}
+
+	/// Unwraps this `MappedFile`, returning the file descriptor
+	///
+	/// The file offset of the descriptor is not changed by reading
+	#[must_use]
+	pub fn into_inner(self) -> OwnedFd {
+		self.fd
+	}
+
+	/// The length of the file when it was opened
+	#[must_use]
+	pub const fn len(&self) -> u64 {
+		self.len
+	}
+
+	/// Returns `true` if the file was empty when it was opened
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	fn window(&self) -> &[u8] {
+		if self.window.len() == 0 {
+			return &[];
+		}
+
+		/* Safety: the mapping is readable and lives as long as `self` */
+		unsafe {
+			slice::from_raw_parts(
+				self.window.as_ptr().cast::<u8>().as_ptr(),
+				self.window.len()
+			)
+		}
+	}
+
+	/// Map the window containing the current position, returning the number
+	/// of bytes made available
+	///
+	/// The mapping starts at the page containing the position, so up to a page
+	/// more than the window size is mapped for the full window size to be
+	/// available past the position
+	fn map_window(&mut self) -> Result<usize> {
+		#[allow(clippy::arithmetic_side_effects)]
+		let start = self.pos - self.pos % page_size() as u64;
+
+		/* unmap first to release the address space */
+		self.window = Map::new();
+		self.window_start = self.pos;
+
+		if start >= self.len {
+			return Ok(0);
+		}
+
+		#[allow(clippy::arithmetic_side_effects, clippy::cast_possible_truncation)]
+		let length = (self.len - start).min(self.window_size.saturating_add(page_size()) as u64);
+
+		#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+		let window = Builder::new(Type::Private, length as usize)
+			.protect(Protection::Read)
+			.fd(self.fd.as_fd())
+			.offset(start as isize)
+			.map()?;
+
+		if let Some(hint) = self.hint {
+			/* hints may be unsupported by the filesystem, which is harmless.
+			 * Safety: none of the hints modify the contents or permissions */
+			let _ = unsafe { window.advise(hint.into()) };
+		}
+
+		self.window = window;
+		self.window_start = start;
+
+		Ok(self.buffer().len())
+	}
+}
+
+#[asynchronous]
+impl Read for MappedFile {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_into!(buf);
+
+		if self.buffer().is_empty() && self.fill().await? == 0 {
+			return Ok(0);
+		}
+
+		let read = read_into_slice(buf, self.buffer());
+
+		self.consume(read);
+
+		Ok(read)
+	}
+}
+
+#[asynchronous]
+impl BufRead for MappedFile {
+	/// Map a new window at the current position if fewer than `amount` bytes
+	/// are available
+	async fn fill_amount(&mut self, amount: usize) -> Result<usize> {
+		assert!(amount <= self.capacity());
+
+		let available = self.buffer().len();
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let window_end = self.window_start + self.window.len() as u64;
+
+		/* nothing more to map if the window already reaches the end of the file */
+		if available >= amount || (self.pos >= self.window_start && window_end >= self.len) {
+			return Ok(0);
+		}
+
+		let mapped = self.map_window()?;
+
+		Ok(mapped.saturating_sub(available))
+	}
+
+	fn capacity(&self) -> usize {
+		self.window_size
+	}
+
+	fn spare_capacity(&self) -> usize {
+		self.window_size.saturating_sub(self.window.len())
+	}
+
+	/// At most [`capacity`] bytes of the window, starting at the current
+	/// position
+	///
+	/// [`capacity`]: BufRead::capacity
+	fn buffer(&self) -> &[u8] {
+		let Some(offset) = self.pos.checked_sub(self.window_start) else {
+			return &[];
+		};
+
+		let buffer = usize::try_from(offset)
+			.ok()
+			.and_then(|offset| self.window().get(offset..))
+			.unwrap_or(&[]);
+
+		&buffer[0..buffer.len().min(self.window_size)]
+	}
+
+	#[allow(clippy::arithmetic_side_effects)]
+	fn consume(&mut self, count: usize) {
+		assert!(count <= self.buffer().len());
+
+		self.pos += count as u64;
+	}
+
+	fn unconsume(&mut self, count: usize) {
+		self.pos = self
+			.pos
+			.checked_sub(count as u64)
+			.filter(|pos| *pos >= self.window_start)
+			.expect("`count` > `self.position()`");
+	}
+
+	#[allow(clippy::arithmetic_side_effects)]
+	fn discard(&mut self) {
+		self.pos += self.buffer().len() as u64;
+	}
+}
+
+#[asynchronous]
+impl Seek for MappedFile {
+	fn stream_len_fast(&self) -> bool {
+		true
+	}
+
+	async fn stream_len(&mut self) -> Result<u64> {
+		Ok(self.len)
+	}
+
+	fn stream_position_fast(&self) -> bool {
+		true
+	}
+
+	async fn stream_position(&mut self) -> Result<u64> {
+		Ok(self.pos)
+	}
+
+	/// Seeking within the current window is free. Seeking past the end of the
+	/// file is allowed, in which case reads return EOF
+	async fn seek(&mut self, seek: SeekFrom) -> Result<u64> {
+		let pos = match seek {
+			SeekFrom::Start(pos) => Some(pos),
+			SeekFrom::Current(rel) => self.pos.checked_add_signed(rel),
+			SeekFrom::End(rel) => self.len.checked_add_signed(rel)
+		};
+
+		self.pos = pos.ok_or_else(
+			|| fmt_error!("Invalid seek to a negative or overflowing position" @ ErrorKind::InvalidInput)
+		)?;
+
+		Ok(self.pos)
+	}
+}
+
+impl AsFd for MappedFile {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl AsRawFd for MappedFile {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
@@ -4,6 +4,7 @@
 
 use std::io::{IoSlice, IoSliceMut, SeekFrom};
 use std::mem::{take, transmute};
+use std::os::fd::BorrowedFd;
 use std::str::from_utf8;
 
 use super::*;
@@ -15,14 +16,23 @@ macro_each!(sealed_trait, (trait Read), (trait BufRead), (trait Write));
 
 pub mod buf_reader;
 pub mod buf_writer;
+pub mod copy;
+pub mod fd;
+pub mod mapped;
+pub mod pipe;
 pub mod read;
+pub mod ready;
 pub mod seek;
 pub mod split;
+pub mod stdio;
 pub mod typed;
 pub mod write;
 
 #[doc(inline)]
-pub use {buf_reader::*, buf_writer::*, read::*, seek::*, split::*, write::*};
+pub use {
+	buf_reader::*, buf_writer::*, copy::*, fd::*, mapped::*, pipe::*, read::*, ready::*, seek::*,
+	split::*, stdio::*, write::*
+};
 
 /// The default buffer size (16 KiB) for buffered I/O
 pub const DEFAULT_BUFFER_SIZE: usize = 0x4000;
@@ -0,0 +1,178 @@
+//! Anonymous pipes and zero copy transfers between file descriptors
+//!
+//! Pipes are created nonblocking. Reads, writes and transfers are attempted
+//! directly, and wait for readiness with [`wait_ready`] when the pipe is empty
+//! or full, so a pending operation does not occupy any thread. The ring
+//! opcodes for splice and tee are defined, but there is no ring driver to
+//! submit them to yet.
+//!
+//! A pipe end is made blocking again when it is converted into a file
+//! descriptor, such as when it is handed to a child process as a [`Stdio`].
+//!
+//! [`Stdio`]: crate::async_std::process::Stdio
+
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd};
+
+use super::*;
+use crate::os::error::OsError;
+use crate::os::fcntl::{set_nonblocking, OpenFlag};
+use crate::os::poll::PollFlag;
+use crate::os::splice::{self as sys, SpliceFlag};
+use crate::os::unistd::pipe2;
+use crate::warn;
+
+/// The read end of an anonymous pipe
+#[derive(Debug)]
+pub struct PipeReader {
+	fd: OwnedFd
+}
+
+/// The write end of an anonymous pipe
+#[derive(Debug)]
+pub struct PipeWriter {
+	fd: OwnedFd
+}
+
+/// Create an anonymous pipe, returning the read and write ends
+///
+/// Both ends are closed on exec. To share an end with a child process, pass
+/// it as one of the child's [`Stdio`]s.
+///
+/// [`Stdio`]: crate::async_std::process::Stdio
+pub fn pipe() -> Result<(PipeReader, PipeWriter)> {
+	let (reader, writer) = pipe2(OpenFlag::CloseOnExec | OpenFlag::NonBlock)?;
+
+	Ok((PipeReader { fd: reader }, PipeWriter { fd: writer }))
+}
+
+macro_rules! pipe_end {
+	($name:ident) => {
+		impl AsFd for $name {
+			fn as_fd(&self) -> BorrowedFd<'_> {
+				self.fd.as_fd()
+			}
+		}
+
+		impl AsRawFd for $name {
+			fn as_raw_fd(&self) -> RawFd {
+				self.fd.as_raw_fd()
+			}
+		}
+
+		impl IntoRawFd for $name {
+			fn into_raw_fd(self) -> RawFd {
+				OwnedFd::from(self).into_raw_fd()
+			}
+		}
+
+		impl From<$name> for OwnedFd {
+			fn from(pipe: $name) -> Self {
+				if let Err(err) = set_nonblocking(pipe.fd.as_fd(), false) {
+					warn!(target: &pipe, "== Failed to make pipe blocking: {:?}", err);
+				}
+
+				pipe.fd
+			}
+		}
+	};
+}
+
+pipe_end!(PipeReader);
+pipe_end!(PipeWriter);
+
+#[asynchronous]
+impl Read for PipeReader {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_nonblocking(self.fd.as_fd(), buf).await
+	}
+
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		Some(self.fd.as_fd())
+	}
+}
+
+#[asynchronous]
+impl Write for PipeWriter {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		write_nonblocking(self.fd.as_fd(), buf).await
+	}
+
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		Some(self.fd.as_fd())
+	}
+}
+
+/// Wait after a transfer between `fd_in` and `fd_out` failed with `EAGAIN`
+///
+/// It isn't known which of the two is an empty or full pipe, so wait for
+/// both. Regular files can't be polled, and are always ready
+#[asynchronous]
+pub(super) async fn wait_transfer(fd_in: BorrowedFd<'_>, fd_out: BorrowedFd<'_>) -> Result<()> {
+	for (fd, events) in [(fd_in, PollFlag::In), (fd_out, PollFlag::Out)] {
+		if let Err(err) = wait_ready(fd, events.into()).await {
+			if err.os_error() != Some(OsError::Perm) {
+				return Err(err);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Move up to `len` bytes from `fd_in` to `fd_out` without copying through
+/// user space. At least one of the descriptors must be a pipe
+///
+/// Offsets apply to non-pipe descriptors, and are advanced by the amount
+/// moved. If `None`, the file offset is used instead.
+///
+/// Returns the number of bytes moved, or zero at end of input
+///
+/// See also [`splice(2)`](https://man7.org/linux/man-pages/man2/splice.2.html)
+#[asynchronous]
+pub async fn splice(
+	fd_in: BorrowedFd<'_>, off_in: Option<&mut i64>, fd_out: BorrowedFd<'_>,
+	off_out: Option<&mut i64>, len: usize
+) -> Result<usize> {
+	if len == 0 {
+		return Ok(0);
+	}
+
+	let (mut off_in, mut off_out) = (off_in, off_out);
+	let flags = SpliceFlag::Move | SpliceFlag::More | SpliceFlag::NonBlock;
+
+	loop {
+		match sys::splice(
+			fd_in,
+			off_in.as_deref_mut(),
+			fd_out,
+			off_out.as_deref_mut(),
+			len,
+			flags
+		) {
+			Ok(moved) => return Ok(moved),
+			Err(OsError::Again) => wait_transfer(fd_in, fd_out).await?,
+			Err(err) => return Err(err.into())
+		}
+	}
+}
+
+/// Duplicate up to `len` bytes from the pipe `fd_in` to the pipe `fd_out`,
+/// leaving them readable from `fd_in`
+///
+/// Returns the number of bytes duplicated, or zero if `fd_in` has no writers
+///
+/// See also [`tee(2)`](https://man7.org/linux/man-pages/man2/tee.2.html)
+#[asynchronous]
+pub async fn tee(fd_in: BorrowedFd<'_>, fd_out: BorrowedFd<'_>, len: usize) -> Result<usize> {
+	if len == 0 {
+		return Ok(0);
+	}
+
+	loop {
+		match sys::tee(fd_in, fd_out, len, SpliceFlag::NonBlock.into()) {
+			Ok(copied) => return Ok(copied),
+			Err(OsError::Again) => wait_transfer(fd_in, fd_out).await?,
+			Err(err) => return Err(err.into())
+		}
+	}
+}
@@ -249,6 +249,17 @@ pub trait Read {
 		false
 	}
 
+	/// Returns the file descriptor backing this reader, if reading from it
+	/// directly is equivalent to calling [`read`]
+	///
+	/// Used by [`copy`] to move data without copying through user space.
+	/// Buffered readers must return `None`, or buffered data would be skipped
+	///
+	/// [`read`]: Read::read
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		None
+	}
+
 	/// Like [`read`], except that it reads into a slice of buffers
 	///
 	/// See also [`std::io::Read::read_vectored`]
@@ -338,6 +349,9 @@ macro_rules! read_wrapper {
 			#[asynchronous(traitfn)]
 			fn is_read_vectored(&self) -> bool;
 
+			#[asynchronous(traitfn)]
+			fn copy_fd(&self) -> ::std::option::Option<::std::os::fd::BorrowedFd<'_>>;
+
 			#[asynchronous(traitfn)]
 			async fn read_vectored(&mut self, bufs: &mut [::std::io::IoSliceMut<'_>]) -> $crate::error::Result<usize>;
 
@@ -0,0 +1,234 @@
+//! Readiness waits on file descriptors that the runtime cannot read or write
+//! asynchronously by itself, such as pipes, pidfds and signalfds
+//!
+//! A single background thread waits on an epoll instance for every
+//! registered descriptor, and completes the waiting task when its descriptor
+//! becomes ready. Any number of tasks can wait at the cost of that one
+//! thread, instead of holding a [`run_blocking`] thread each.
+//!
+//! [`run_blocking`]: crate::async_std::blocking::run_blocking
+
+use std::collections::BTreeMap;
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use enumflags2::BitFlags;
+
+use super::*;
+use crate::os::epoll::{self, ControlOp, Event, EventPoll};
+use crate::os::error::OsError;
+use crate::os::poll::PollFlag;
+use crate::os::signal::*;
+use crate::os::signalfd::signal_set;
+use crate::os::unistd;
+use crate::pointer::*;
+use crate::{error, warn};
+
+struct Waiter {
+	fd: RawFd,
+	request: ReqPtr<OsResult<BitFlags<PollFlag>>>
+}
+
+struct Poller {
+	epoll: EventPoll,
+	waiters: Mutex<(u64, BTreeMap<u64, Waiter>)>
+}
+
+impl Poller {
+	fn get() -> Result<&'static Self> {
+		static POLLER: OnceLock<Poller> = OnceLock::new();
+
+		if let Some(poller) = POLLER.get() {
+			return Ok(poller);
+		}
+
+		let epoll = EventPoll::new(epoll::CreateFlag::CloseOnExec.into())?;
+		let mut created = false;
+		let poller = POLLER.get_or_init(|| {
+			created = true;
+
+			Self { epoll, waiters: Mutex::new((0, BTreeMap::new())) }
+		});
+
+		if created {
+			thread::Builder::new()
+				.name("xx-poller".to_string())
+				.spawn(|| poller.run())?;
+		}
+
+		Ok(poller)
+	}
+
+	/// The poller only receives synchronous faults, so that process directed
+	/// signals are left for the threads that expect them
+	fn block_signals(&self) {
+		let unblocked = signal_set(&[
+			Signal::SegmentationViolation,
+			Signal::Bus,
+			Signal::IllegalInstruction,
+			Signal::FloatingPointException,
+			Signal::Trap
+		]);
+
+		if let Err(err) = pthread_set_sigmask(SignalHow::SetMask, Some(&[!unblocked]), None) {
+			warn!(target: self, "== Failed to set poller signal mask: {:?}", err);
+		}
+	}
+
+	#[allow(clippy::unwrap_used)]
+	fn run(&self) {
+		const NO_TIMEOUT: Duration = Duration::from_secs(0xffff_ffff);
+
+		self.block_signals();
+
+		let mut events = [Event::default(); 64];
+
+		loop {
+			let count = match self.epoll.wait(&mut events, NO_TIMEOUT) {
+				Ok(count) => count as usize,
+				Err(OsError::Intr) => continue,
+				Err(err) => {
+					error!(target: self, "== Failed to wait for events: {:?}", err);
+
+					return;
+				}
+			};
+
+			for event in &events[0..count] {
+				let Event { events, data } = *event;
+				let waiter = self.waiters.lock().unwrap().1.remove(&data);
+
+				/* the wait was cancelled after the event arrived */
+				let Some(waiter) = waiter else { continue };
+
+				self.remove(waiter.fd);
+
+				/* Safety: the request was registered and not yet completed */
+				unsafe {
+					Request::complete(waiter.request, Ok(BitFlags::from_bits_truncate(events)))
+				};
+			}
+		}
+	}
+
+	fn remove(&self, fd: RawFd) {
+		/* Safety: registered fds stay open until their wait completes */
+		let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+
+		if let Err(err) = self.epoll.ctl(ControlOp::Del, fd, &mut Event::default()) {
+			warn!(target: self, "== Failed to remove fd from epoll: {:?}", err);
+		}
+	}
+
+	#[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)]
+	fn register(
+		&self, fd: BorrowedFd<'_>, events: BitFlags<PollFlag>,
+		request: ReqPtr<OsResult<BitFlags<PollFlag>>>
+	) -> OsResult<u64> {
+		let mut waiters = self.waiters.lock().unwrap();
+		let (next, waiters) = &mut *waiters;
+		let token = *next;
+
+		let mut event = Event {
+			events: events.bits() | epoll::PollFlag::OneShot as u32,
+			data: token
+		};
+
+		self.epoll.ctl(ControlOp::Add, fd, &mut event)?;
+
+		*next += 1;
+		waiters.insert(token, Waiter { fd: fd.as_raw_fd(), request });
+
+		Ok(token)
+	}
+
+	#[future]
+	unsafe fn wait(
+		&self, fd: BorrowedFd<'_>, events: BitFlags<PollFlag>, request: _
+	) -> OsResult<BitFlags<PollFlag>> {
+		#[cancel]
+		fn cancel(&self, token: u64) -> Result<()> {
+			#[allow(clippy::unwrap_used)]
+			let waiter = self.waiters.lock().unwrap().1.remove(&token);
+
+			/* the poller thread already took the waiter, and completes it */
+			let Some(waiter) = waiter else { return Ok(()) };
+
+			self.remove(waiter.fd);
+
+			/* Safety: the waiter was removed, so there won't be another completion */
+			unsafe { Request::complete(waiter.request, Ok(BitFlags::default())) };
+
+			Ok(())
+		}
+
+		match self.register(fd, events, request) {
+			Ok(token) => Progress::Pending(cancel(self, token)),
+			Err(err) => Progress::Done(Err(err))
+		}
+	}
+}
+
+/// Suspend until `fd` is ready for any of `events`, returning the events
+/// that are ready
+///
+/// Errors and hang ups are always reported, even if not requested. Only one
+/// task may wait on a given descriptor at a time.
+///
+/// Returns an [`Interrupted`] error if the task is interrupted while waiting
+///
+/// # Cancel safety
+///
+/// This function is cancel safe. Once the interrupt is cleared, call this
+/// function again to resume the operation.
+///
+/// [`Interrupted`]: ErrorKind::Interrupted
+#[asynchronous]
+pub async fn wait_ready(
+	fd: BorrowedFd<'_>, events: BitFlags<PollFlag>
+) -> Result<BitFlags<PollFlag>> {
+	check_interrupt().await?;
+
+	let poller = Poller::get()?;
+
+	/* Safety: the fd is borrowed until the future completes */
+	let ready = block_on_thread_safe(unsafe { poller.wait(fd, events) }).await?;
+
+	if ready.is_empty() {
+		return Err(interrupt_error().await);
+	}
+
+	Ok(ready)
+}
+
+/// Read from the nonblocking `fd`, waiting for it to become readable if no
+/// data is available
+#[asynchronous]
+pub async fn read_nonblocking(fd: BorrowedFd<'_>, buf: &mut [u8]) -> Result<usize> {
+	read_into!(buf);
+
+	loop {
+		match unistd::read(fd, (&mut *buf).into()) {
+			Ok(read) => return Ok(length_check(buf, read)),
+			Err(OsError::Again) => wait_ready(fd, PollFlag::In.into()).await?,
+			Err(err) => return Err(err.into())
+		};
+	}
+}
+
+/// Write to the nonblocking `fd`, waiting for it to become writable if it is
+/// full
+#[asynchronous]
+pub async fn write_nonblocking(fd: BorrowedFd<'_>, buf: &[u8]) -> Result<usize> {
+	write_from!(buf);
+
+	loop {
+		match unistd::write(fd, buf.into()) {
+			Ok(wrote) => return Ok(length_check(buf, wrote)),
+			Err(OsError::Again) => wait_ready(fd, PollFlag::Out.into()).await?,
+			Err(err) => return Err(err.into())
+		};
+	}
+}
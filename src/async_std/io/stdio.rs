@@ -0,0 +1,267 @@
+//! Async handles to the standard streams
+//!
+//! When a stream is a pipe, terminal, socket or other character device, reads
+//! and writes wait for it with [`wait_ready`] first, so an idle stream does
+//! not occupy any thread. The stream is left blocking, since its open file
+//! description is shared with the parent process, and a write is limited to
+//! `PIPE_BUF` bytes, which a writable pipe accepts without blocking.
+//!
+//! Regular files are always reported ready, so reads and writes on them are
+//! performed on the [`run_blocking`] thread pool instead.
+//!
+//! Each stream is protected by a process-wide lock. A single [`Write::write`]
+//! on a handle writes the entire buffer while holding the lock, so lines
+//! written by concurrent tasks are never interleaved.
+//!
+//! [`run_blocking`]: crate::async_std::blocking::run_blocking
+
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::sync::{OnceLock, TryLockError};
+
+use super::*;
+use crate::async_std::sync::{Mutex, MutexGuard};
+use crate::os::dirent::FileType;
+use crate::os::poll::PollFlag;
+use crate::os::stat::{statx_fd, Statx, StatxMask};
+use crate::os::unistd;
+
+/// The largest write that is atomic, and that a writable pipe accepts without
+/// blocking
+const PIPE_BUF: usize = 4096;
+
+#[allow(unsafe_code)]
+const fn borrow(fd: RawFd) -> BorrowedFd<'static> {
+	/* Safety: the standard streams are never closed by us */
+	unsafe { BorrowedFd::borrow_raw(fd) }
+}
+
+/// Whether `fd` can be waited for with [`wait_ready`]. Regular files are
+/// always ready, and a stream of unknown type falls back to the pool
+fn is_pollable(fd: BorrowedFd<'_>) -> bool {
+	let mut statx = Statx::default();
+	let mask = StatxMask::Type | StatxMask::Mode;
+
+	statx_fd(fd, 0, mask.bits(), &mut statx).is_ok() &&
+		matches!(
+			statx.file_type(),
+			Some(FileType::Fifo | FileType::Character | FileType::Socket)
+		)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct StdioFd {
+	fd: BorrowedFd<'static>,
+	pollable: bool
+}
+
+impl StdioFd {
+	fn new(fd: RawFd) -> Self {
+		let fd = borrow(fd);
+
+		Self { fd, pollable: is_pollable(fd) }
+	}
+}
+
+#[asynchronous]
+impl Read for StdioFd {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		if !self.pollable {
+			return read_fd(self.fd, buf).await;
+		}
+
+		read_into!(buf);
+
+		/* doesn't block once readable, unless another process reads the data
+		 * first */
+		wait_ready(self.fd, PollFlag::In.into()).await?;
+
+		let read = unistd::read(self.fd, (&mut *buf).into())?;
+
+		Ok(length_check(buf, read))
+	}
+}
+
+#[asynchronous]
+impl Write for StdioFd {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		if !self.pollable {
+			return write_fd(self.fd, buf).await;
+		}
+
+		write_from!(buf);
+
+		let buf = &buf[..buf.len().min(PIPE_BUF)];
+
+		wait_ready(self.fd, PollFlag::Out.into()).await?;
+
+		let wrote = unistd::write(self.fd, buf.into())?;
+
+		Ok(length_check(buf, wrote))
+	}
+
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		Some(self.fd)
+	}
+}
+
+#[asynchronous]
+async fn lock<T>(mutex: &'static Mutex<T>) -> Result<MutexGuard<'static, T>> {
+	match mutex.lock().await {
+		Ok(guard) => Ok(guard),
+
+		/* a panic while holding the lock cannot corrupt a stream */
+		Err(TryLockError::Poisoned(poison)) => Ok(poison.into_inner()),
+		Err(TryLockError::WouldBlock) => Err(interrupt_error().await)
+	}
+}
+
+/// A handle to the standard input of the process
+///
+/// See also [`std::io::Stdin`]
+#[derive(Clone, Copy)]
+pub struct Stdin {
+	inner: &'static Mutex<BufReader<StdioFd>>
+}
+
+/// A locked reference to [`Stdin`], giving exclusive access to buffered
+/// reads such as [`BufRead::read_line`]
+pub struct StdinLock<'a> {
+	guard: MutexGuard<'a, BufReader<StdioFd>>
+}
+
+/// Returns a handle to the standard input of the process
+pub fn stdin() -> Stdin {
+	static STDIN: OnceLock<Mutex<BufReader<StdioFd>>> = OnceLock::new();
+
+	Stdin {
+		inner: STDIN.get_or_init(|| Mutex::new(BufReader::new(StdioFd::new(0))))
+	}
+}
+
+#[asynchronous]
+impl Stdin {
+	/// Lock the handle, suspending until no other task holds it
+	///
+	/// Returns an [`Interrupted`] error if the task is interrupted while
+	/// waiting
+	///
+	/// [`Interrupted`]: ErrorKind::Interrupted
+	pub async fn lock(&self) -> Result<StdinLock<'static>> {
+		Ok(StdinLock { guard: lock(self.inner).await? })
+	}
+
+	/// Lock the handle and read a line into `buf`
+	///
+	/// See also [`BufRead::read_line`]
+	pub async fn read_line(&self, buf: &mut String) -> Result<Option<usize>> {
+		self.lock().await?.read_line(buf).await
+	}
+}
+
+#[asynchronous]
+impl Read for Stdin {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		self.lock().await?.read(buf).await
+	}
+}
+
+#[asynchronous]
+impl Read for StdinLock<'_> {
+	read_wrapper! {
+		inner = guard;
+		mut inner = guard;
+	}
+}
+
+#[asynchronous]
+impl BufRead for StdinLock<'_> {
+	bufread_wrapper! {
+		inner = guard;
+		mut inner = guard;
+	}
+}
+
+impl AsFd for Stdin {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		borrow(0)
+	}
+}
+
+impl AsRawFd for Stdin {
+	fn as_raw_fd(&self) -> RawFd {
+		0
+	}
+}
+
+macro_rules! output_stream {
+	($name:ident, $lock:ident, $func:ident, $fd:literal, $desc:literal) => {
+		#[doc = concat!("A handle to the ", $desc, " of the process")]
+		#[doc = ""]
+		#[doc = concat!("See also [`std::io::", stringify!($name), "`]")]
+		#[derive(Clone, Copy)]
+		pub struct $name {
+			inner: &'static Mutex<StdioFd>
+		}
+
+		#[doc = concat!("A locked reference to [`", stringify!($name), "`]")]
+		pub struct $lock<'a> {
+			guard: MutexGuard<'a, StdioFd>
+		}
+
+		#[doc = concat!("Returns a handle to the ", $desc, " of the process")]
+		pub fn $func() -> $name {
+			static STREAM: OnceLock<Mutex<StdioFd>> = OnceLock::new();
+
+			$name {
+				inner: STREAM.get_or_init(|| Mutex::new(StdioFd::new($fd)))
+			}
+		}
+
+		#[asynchronous]
+		impl $name {
+			/// Lock the handle, suspending until no other task holds it
+			///
+			/// Writes made through the lock are not interleaved with writes
+			/// from other tasks
+			///
+			/// Returns an [`Interrupted`] error if the task is interrupted
+			/// while waiting
+			///
+			/// [`Interrupted`]: ErrorKind::Interrupted
+			pub async fn lock(&self) -> Result<$lock<'static>> {
+				Ok($lock { guard: lock(self.inner).await? })
+			}
+		}
+
+		#[asynchronous]
+		impl Write for $name {
+			/// Writes all of `buf` while holding the lock
+			async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+				self.lock().await?.try_write_all(buf).await
+			}
+		}
+
+		#[asynchronous]
+		impl Write for $lock<'_> {
+			write_wrapper! {
+				inner = guard;
+				mut inner = guard;
+			}
+		}
+
+		impl AsFd for $name {
+			fn as_fd(&self) -> BorrowedFd<'_> {
+				borrow($fd)
+			}
+		}
+
+		impl AsRawFd for $name {
+			fn as_raw_fd(&self) -> RawFd {
+				$fd
+			}
+		}
+	};
+}
+
+output_stream!(Stdout, StdoutLock, stdout, 1, "standard output");
+output_stream!(Stderr, StderrLock, stderr, 2, "standard error");
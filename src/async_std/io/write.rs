@@ -122,6 +122,17 @@ pub trait Write {
 		false
 	}
 
+	/// Returns the file descriptor backing this writer, if writing to it
+	/// directly is equivalent to calling [`write`]
+	///
+	/// Used by [`copy`] to move data without copying through user space.
+	/// Buffered writers must return `None`, or data would be reordered
+	///
+	/// [`write`]: Write::write
+	fn copy_fd(&self) -> Option<BorrowedFd<'_>> {
+		None
+	}
+
 	/// Like [`write`], except that it writes from a slice of buffers
 	///
 	/// See also [`std::io::Write::write_vectored`]
@@ -210,6 +221,9 @@ macro_rules! write_wrapper {
 			#[asynchronous(traitfn)]
 			fn is_write_vectored(&self) -> bool;
 
+			#[asynchronous(traitfn)]
+			fn copy_fd(&self) -> ::std::option::Option<::std::os::fd::BorrowedFd<'_>>;
+
 			#[asynchronous(traitfn)]
 			async fn write_vectored(&mut self, bufs: &[::std::io::IoSlice<'_>]) -> $crate::error::Result<usize>;
 
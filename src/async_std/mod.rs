@@ -3,8 +3,12 @@
 use crate::coroutines::*;
 use crate::error::*;
 
+pub mod blocking;
+pub mod fs;
 pub mod io;
 pub mod iterator;
+pub mod process;
+pub mod signal;
 pub mod sync;
 
 #[doc(inline)]
@@ -0,0 +1,906 @@
+//! The async equivalent of [`std::process`]
+//!
+//! Children are spawned the way `posix_spawn` does it: with `CLONE_VM` and
+//! `CLONE_VFORK`, sharing the parent's memory while running on a small stack
+//! of their own, so no page tables are copied however large the parent is.
+//! The parent is suspended until the child calls `exec` or exits, so spawn
+//! errors such as a missing executable are reported from [`Command::spawn`].
+//!
+//! Waiting is done on a pidfd, so no `SIGCHLD` handler is installed and other
+//! children of the process are never reaped by accident. The pidfd and the
+//! parent's ends of the pipes are polled for readiness, so waiting children
+//! do not occupy any [`run_blocking`] threads.
+//!
+//! [`run_blocking`]: super::blocking::run_blocking
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::fmt::{self, Display, Formatter};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use enumflags2::BitFlags;
+
+use super::io::*;
+use super::*;
+use crate::os::epoll::{self, ControlOp, Event, EventPoll};
+use crate::os::error::OsError;
+use crate::os::fcntl::*;
+use crate::os::mman::{Builder, Flag, Protection, Type};
+use crate::os::poll::*;
+use crate::os::process::*;
+use crate::os::signal::*;
+use crate::os::{unistd, INVALID_FD};
+use crate::pointer::*;
+use crate::warn;
+
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// The exit code of a child that failed to `exec`
+const EXEC_FAILED: i32 = 127;
+
+/// The size of the stack the child runs on until it calls `exec`
+const CHILD_STACK_SIZE: usize = 0x10000;
+
+/// The highest signal number
+const MAX_SIGNAL: i32 = 64;
+
+#[derive(Debug)]
+enum StdioKind {
+	Inherit,
+	Null,
+	Piped,
+	Fd(OwnedFd)
+}
+
+/// Describes what to do with a standard stream of a child process
+///
+/// See also [`std::process::Stdio`]
+#[derive(Debug)]
+pub struct Stdio(StdioKind);
+
+impl Stdio {
+	/// The stream is inherited from the parent
+	#[must_use]
+	pub const fn inherit() -> Self {
+		Self(StdioKind::Inherit)
+	}
+
+	/// The stream is connected to `/dev/null`
+	#[must_use]
+	pub const fn null() -> Self {
+		Self(StdioKind::Null)
+	}
+
+	/// A new pipe is created between the parent and the child
+	#[must_use]
+	pub const fn piped() -> Self {
+		Self(StdioKind::Piped)
+	}
+
+	/// Returns the fd for the child and the parent's end of the pipe, if any
+	///
+	/// All returned fds are close-on-exec
+	fn open(&self, input: bool) -> Result<(Option<OwnedFd>, Option<OwnedFd>)> {
+		let fds = match &self.0 {
+			StdioKind::Inherit => (None, None),
+			StdioKind::Null => {
+				let mut flags = BitFlags::from(OpenFlag::CloseOnExec);
+
+				if !input {
+					flags |= OpenFlag::WriteOnly;
+				}
+
+				let fd = unistd::openat(None, c"/dev/null", flags.bits(), 0)?;
+
+				(Some(fd), None)
+			}
+
+			StdioKind::Piped => {
+				let (read, write) = unistd::pipe2(OpenFlag::CloseOnExec.into())?;
+
+				if input {
+					(Some(read), Some(write))
+				} else {
+					(Some(write), Some(read))
+				}
+			}
+
+			StdioKind::Fd(fd) => (Some(fd.try_clone()?), None)
+		};
+
+		Ok(fds)
+	}
+}
+
+impl From<OwnedFd> for Stdio {
+	fn from(fd: OwnedFd) -> Self {
+		Self(StdioKind::Fd(fd))
+	}
+}
+
+impl From<AsyncFd> for Stdio {
+	fn from(fd: AsyncFd) -> Self {
+		Self(StdioKind::Fd(fd.into_inner()))
+	}
+}
+
+impl From<PipeReader> for Stdio {
+	fn from(pipe: PipeReader) -> Self {
+		Self(StdioKind::Fd(pipe.into()))
+	}
+}
+
+impl From<PipeWriter> for Stdio {
+	fn from(pipe: PipeWriter) -> Self {
+		Self(StdioKind::Fd(pipe.into()))
+	}
+}
+
+/// The status of a finished child process
+///
+/// See also [`std::process::ExitStatus`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExitStatus {
+	code: i32,
+	status: i32
+}
+
+impl ExitStatus {
+	const fn new(code: i32, status: i32) -> Self {
+		Self { code, status }
+	}
+
+	/// Returns `true` if the child exited with a status of zero
+	#[must_use]
+	pub const fn success(&self) -> bool {
+		matches!(self.code(), Some(0))
+	}
+
+	/// The exit code of the child, or `None` if it was killed by a signal
+	#[must_use]
+	pub const fn code(&self) -> Option<i32> {
+		if self.code == ChildCode::Exited as i32 {
+			Some(self.status)
+		} else {
+			None
+		}
+	}
+
+	/// The signal that killed the child, if any
+	#[must_use]
+	pub const fn signal(&self) -> Option<i32> {
+		if self.code == ChildCode::Killed as i32 || self.core_dumped() {
+			Some(self.status)
+		} else {
+			None
+		}
+	}
+
+	#[must_use]
+	pub const fn core_dumped(&self) -> bool {
+		self.code == ChildCode::Dumped as i32
+	}
+}
+
+impl Display for ExitStatus {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		if let Some(code) = self.code() {
+			return write!(fmt, "exit status: {}", code);
+		}
+
+		write!(fmt, "signal: {}", self.status)?;
+
+		if self.core_dumped() {
+			fmt.write_str(" (core dumped)")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The output of a finished child process
+///
+/// See also [`std::process::Output`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Output {
+	pub status: ExitStatus,
+	pub stdout: Vec<u8>,
+	pub stderr: Vec<u8>
+}
+
+macro_rules! child_stream {
+	($name:ident, $stream:literal) => {
+		#[doc = concat!("The parent's end of a child's ", $stream, " pipe")]
+		///
+		/// The pipe is nonblocking while owned by the parent, and is made
+		/// blocking again when converted back into a file descriptor
+		#[derive(Debug)]
+		pub struct $name(OwnedFd);
+
+		impl $name {
+			fn new(fd: OwnedFd) -> Result<Self> {
+				set_nonblocking(fd.as_fd(), true)?;
+
+				Ok(Self(fd))
+			}
+		}
+
+		impl AsFd for $name {
+			fn as_fd(&self) -> BorrowedFd<'_> {
+				self.0.as_fd()
+			}
+		}
+
+		impl AsRawFd for $name {
+			fn as_raw_fd(&self) -> RawFd {
+				self.0.as_raw_fd()
+			}
+		}
+
+		impl From<$name> for OwnedFd {
+			fn from(stream: $name) -> Self {
+				if let Err(err) = set_nonblocking(stream.0.as_fd(), false) {
+					warn!(target: &stream, "== Failed to make pipe blocking: {:?}", err);
+				}
+
+				stream.0
+			}
+		}
+
+		impl From<$name> for Stdio {
+			fn from(stream: $name) -> Self {
+				OwnedFd::from(stream).into()
+			}
+		}
+	};
+}
+
+child_stream!(ChildStdin, "stdin");
+child_stream!(ChildStdout, "stdout");
+child_stream!(ChildStderr, "stderr");
+
+#[asynchronous]
+impl Write for ChildStdin {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		write_nonblocking(self.0.as_fd(), buf).await
+	}
+}
+
+#[asynchronous]
+impl Read for ChildStdout {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_nonblocking(self.0.as_fd(), buf).await
+	}
+}
+
+#[asynchronous]
+impl Read for ChildStderr {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_nonblocking(self.0.as_fd(), buf).await
+	}
+}
+
+fn wait_pidfd(
+	pidfd: BorrowedFd<'_>, options: BitFlags<WaitOption>
+) -> OsResult<Option<ExitStatus>> {
+	let mut info = SigInfo::default();
+
+	waitid(
+		IdType::PidFd,
+		pidfd.as_raw_fd(),
+		&mut info,
+		options | WaitOption::Exited,
+		None
+	)?;
+
+	/* Safety: waitid fills in the child fields */
+	let child = unsafe { info.fields.child };
+
+	if child.pid == 0 {
+		return Ok(None);
+	}
+
+	Ok(Some(ExitStatus::new(info.code, child.status)))
+}
+
+/// Read both nonblocking streams until EOF, without letting either pipe fill
+/// up
+#[asynchronous]
+async fn read_to_end_both(
+	stdout: Option<BorrowedFd<'_>>, stderr: Option<BorrowedFd<'_>>
+) -> Result<(Vec<u8>, Vec<u8>)> {
+	let epoll = EventPoll::new(epoll::CreateFlag::CloseOnExec.into())?;
+	let mut fds = [stdout, stderr];
+	let mut outputs = [Vec::new(), Vec::new()];
+	let mut buf = [0; DEFAULT_BUFFER_SIZE];
+
+	for fd in fds.iter().flatten() {
+		let mut event = Event { events: PollFlag::In as u32, data: 0 };
+
+		epoll.ctl(ControlOp::Add, *fd, &mut event)?;
+	}
+
+	while fds.iter().any(Option::is_some) {
+		let mut blocked = true;
+
+		for (fd, output) in fds.iter_mut().zip(&mut outputs) {
+			let Some(borrowed) = fd else { continue };
+
+			match unistd::read(*borrowed, (&mut buf[..]).into()) {
+				Ok(0) => {
+					epoll.ctl(ControlOp::Del, *borrowed, &mut Event::default())?;
+
+					*fd = None;
+				}
+
+				Ok(read) => output.extend_from_slice(&buf[0..read]),
+				Err(OsError::Again) => continue,
+				Err(OsError::Intr) => (),
+				Err(err) => return Err(err.into())
+			}
+
+			blocked = false;
+		}
+
+		/* the epoll fd is readable once either stream is */
+		if blocked {
+			wait_ready(epoll.fd(), PollFlag::In.into()).await?;
+		}
+	}
+
+	let [stdout, stderr] = outputs;
+
+	Ok((stdout, stderr))
+}
+
+/// A spawned child process
+///
+/// Dropping a `Child` does not kill or wait for the process
+///
+/// See also [`std::process::Child`]
+#[derive(Debug)]
+pub struct Child {
+	pid: i32,
+	pidfd: OwnedFd,
+	status: Option<ExitStatus>,
+	stdin: Option<ChildStdin>,
+	stdout: Option<ChildStdout>,
+	stderr: Option<ChildStderr>
+}
+
+#[asynchronous]
+impl Child {
+	/// The process id of the child
+	#[must_use]
+	pub const fn id(&self) -> i32 {
+		self.pid
+	}
+
+	/// The pidfd referring to the child, valid even after it is reaped
+	#[must_use]
+	pub fn pidfd(&self) -> BorrowedFd<'_> {
+		self.pidfd.as_fd()
+	}
+
+	pub fn stdin(&mut self) -> Option<&mut ChildStdin> {
+		self.stdin.as_mut()
+	}
+
+	pub fn stdout(&mut self) -> Option<&mut ChildStdout> {
+		self.stdout.as_mut()
+	}
+
+	pub fn stderr(&mut self) -> Option<&mut ChildStderr> {
+		self.stderr.as_mut()
+	}
+
+	pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+		self.stdin.take()
+	}
+
+	pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+		self.stdout.take()
+	}
+
+	pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+		self.stderr.take()
+	}
+
+	/// Send `signal` to the child
+	///
+	/// Does nothing if the child has already been waited for
+	pub fn signal(&self, signal: Signal) -> Result<()> {
+		if self.status.is_none() {
+			pidfd_send_signal(self.pidfd.as_fd(), signal, None, 0)?;
+		}
+
+		Ok(())
+	}
+
+	/// Forcibly kill the child with `SIGKILL`
+	///
+	/// See also [`std::process::Child::kill`]
+	pub fn kill(&self) -> Result<()> {
+		self.signal(Signal::Kill)
+	}
+
+	/// Returns the exit status of the child if it has exited, without
+	/// suspending
+	pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+		if self.status.is_none() {
+			self.status = wait_pidfd(self.pidfd.as_fd(), WaitOption::NoHang.into())?;
+		}
+
+		Ok(self.status)
+	}
+
+	/// Wait for the child to exit, closing its stdin first so that it does
+	/// not wait for more input
+	///
+	/// # Cancel safety
+	///
+	/// This function is cancel safe. Once the interrupt is cleared, call this
+	/// function again to resume the operation.
+	pub async fn wait(&mut self) -> Result<ExitStatus> {
+		drop(self.stdin.take());
+
+		loop {
+			if let Some(status) = self.try_wait()? {
+				return Ok(status);
+			}
+
+			/* the pidfd becomes readable once the child exits */
+			wait_ready(self.pidfd.as_fd(), PollFlag::In.into()).await?;
+		}
+	}
+
+	/// Wait for the child to exit, collecting all of its stdout and stderr
+	///
+	/// Only the streams that were piped are collected
+	///
+	/// # Cancel safety
+	///
+	/// This function is not cancel safe. Output is lost on interrupt, since
+	/// an error is returned.
+	pub async fn wait_with_output(mut self) -> Result<Output> {
+		drop(self.stdin.take());
+
+		let (stdout, stderr) = (self.stdout.take(), self.stderr.take());
+		let fds = (
+			stdout.as_ref().map(AsFd::as_fd),
+			stderr.as_ref().map(AsFd::as_fd)
+		);
+
+		let (stdout, stderr) = read_to_end_both(fds.0, fds.1).await?;
+		let status = self.wait().await?;
+
+		Ok(Output { status, stdout, stderr })
+	}
+}
+
+/// Everything the child needs to exec, prepared by the parent so that the
+/// child never allocates
+struct Exec<'a> {
+	path: &'a CStr,
+	argv: Ptr<Ptr<()>>,
+	envp: Ptr<Ptr<()>>,
+	cwd: Option<&'a CStr>,
+	stdio: [Option<BorrowedFd<'a>>; 3],
+	error: Cell<Option<OsError>>
+}
+
+impl Exec<'_> {
+	/// Only returns on failure
+	fn try_exec(&self) -> OsResult<()> {
+		let mut stdio = self.stdio;
+
+		/* a source that is itself one of the standard streams would be
+		 * overwritten by an earlier dup3, so move it out of the way first */
+		for fd in stdio.iter_mut().flatten() {
+			if fd.as_raw_fd() < 3 {
+				/* Safety: the copy is close-on-exec */
+				let copy = unsafe { fcntl(*fd, FcntlCmd::DupFdCloExec, 3) }?;
+
+				/* Safety: the copy stays open until exec */
+				*fd = unsafe { BorrowedFd::borrow_raw(copy) };
+			}
+		}
+
+		for (target, fd) in stdio.iter().enumerate() {
+			let Some(fd) = fd else { continue };
+
+			#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+			let target = target as RawFd;
+
+			/* Safety: the standard streams are replaced in the child only */
+			unsafe { unistd::dup3(*fd, target, BitFlags::default()) }?;
+		}
+
+		if let Some(cwd) = self.cwd {
+			unistd::chdir(cwd)?;
+		}
+
+		reset_signals()?;
+		pthread_set_sigmask(SignalHow::SetMask, Some(&[0]), None)?;
+
+		/* Safety: argv and envp are null terminated arrays of strings */
+		Err(unsafe { execve(self.path, self.argv, self.envp) })
+	}
+
+	fn run(&self) -> ! {
+		if let Err(err) = self.try_exec() {
+			/* the parent reads this once the child exits */
+			self.error.set(Some(err));
+		}
+
+		exit_process(EXEC_FAILED)
+	}
+}
+
+/// The entry point of the child
+///
+/// # Safety
+/// `exec` must point to an [`Exec`] owned by the suspended parent
+unsafe extern "C" fn start_child(exec: MutPtr<()>) -> i32 {
+	/* Safety: guaranteed by caller */
+	unsafe { exec.cast::<Exec<'_>>().as_ref() }.run()
+}
+
+/// Reset the signals the parent handles to their default action
+///
+/// The child shares the parent's memory, so the parent's handlers must not
+/// run in it. SIGPIPE is reset as well, since the Rust runtime ignores it and
+/// ignored signals survive exec
+fn reset_signals() -> OsResult<()> {
+	for signal in 1..=MAX_SIGNAL {
+		let mut action = SigAction::default();
+
+		/* fails for the signals reserved by libc */
+		if sig_action(signal, None, Some(&mut action)).is_err() {
+			continue;
+		}
+
+		/* Safety: every variant is a pointer sized value, and null is `None` */
+		let handler = unsafe { action.handler.handler };
+
+		let Some(handler) = handler else { continue };
+
+		if handler as usize != SigHandlers::Ignore as usize || signal == Signal::Pipe as i32 {
+			sig_action(signal, Some(&SigAction::default()), None)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn null_terminated(strings: &[CString]) -> Vec<Ptr<()>> {
+	strings
+		.iter()
+		.map(|str| ptr!(str.as_ptr()).cast())
+		.chain(Some(Ptr::null()))
+		.collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+	fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// A process builder
+///
+/// See also [`std::process::Command`]
+#[derive(Debug)]
+pub struct Command {
+	program: OsString,
+	args: Vec<OsString>,
+	env: BTreeMap<OsString, Option<OsString>>,
+	env_clear: bool,
+	cwd: Option<PathBuf>,
+	stdin: Option<Stdio>,
+	stdout: Option<Stdio>,
+	stderr: Option<Stdio>
+}
+
+#[asynchronous]
+impl Command {
+	/// Create a new command for `program`
+	///
+	/// If `program` is not a path, it is searched for in `PATH`
+	pub fn new<S>(program: S) -> Self
+	where
+		S: AsRef<OsStr>
+	{
+		let program = program.as_ref().to_os_string();
+
+		Self {
+			args: vec![program.clone()],
+			program,
+			env: BTreeMap::new(),
+			env_clear: false,
+			cwd: None,
+			stdin: None,
+			stdout: None,
+			stderr: None
+		}
+	}
+
+	pub fn arg<S>(&mut self, arg: S) -> &mut Self
+	where
+		S: AsRef<OsStr>
+	{
+		self.args.push(arg.as_ref().to_os_string());
+		self
+	}
+
+	pub fn args<I, S>(&mut self, args: I) -> &mut Self
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>
+	{
+		for arg in args {
+			self.arg(arg);
+		}
+
+		self
+	}
+
+	pub fn env<K, V>(&mut self, key: K, value: V) -> &mut Self
+	where
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>
+	{
+		self.env.insert(
+			key.as_ref().to_os_string(),
+			Some(value.as_ref().to_os_string())
+		);
+
+		self
+	}
+
+	pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+	where
+		I: IntoIterator<Item = (K, V)>,
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>
+	{
+		for (key, value) in vars {
+			self.env(key, value);
+		}
+
+		self
+	}
+
+	pub fn env_remove<K>(&mut self, key: K) -> &mut Self
+	where
+		K: AsRef<OsStr>
+	{
+		self.env.insert(key.as_ref().to_os_string(), None);
+		self
+	}
+
+	/// Do not inherit any environment variables from the parent
+	pub fn env_clear(&mut self) -> &mut Self {
+		self.env.clear();
+		self.env_clear = true;
+		self
+	}
+
+	pub fn current_dir<P>(&mut self, dir: P) -> &mut Self
+	where
+		P: AsRef<Path>
+	{
+		self.cwd = Some(dir.as_ref().to_path_buf());
+		self
+	}
+
+	pub fn stdin<T>(&mut self, stdin: T) -> &mut Self
+	where
+		T: Into<Stdio>
+	{
+		self.stdin = Some(stdin.into());
+		self
+	}
+
+	pub fn stdout<T>(&mut self, stdout: T) -> &mut Self
+	where
+		T: Into<Stdio>
+	{
+		self.stdout = Some(stdout.into());
+		self
+	}
+
+	pub fn stderr<T>(&mut self, stderr: T) -> &mut Self
+	where
+		T: Into<Stdio>
+	{
+		self.stderr = Some(stderr.into());
+		self
+	}
+
+	#[must_use]
+	pub fn get_program(&self) -> &OsStr {
+		&self.program
+	}
+
+	fn get_env(&self, key: &OsStr) -> Option<OsString> {
+		match self.env.get(key) {
+			Some(value) => value.clone(),
+			None if self.env_clear => None,
+			None => env::var_os(key)
+		}
+	}
+
+	fn resolve_program(&self) -> Result<CString> {
+		if self.program.as_bytes().contains(&b'/') {
+			return Ok(CString::new(self.program.as_bytes())?);
+		}
+
+		let path = self
+			.get_env(OsStr::new("PATH"))
+			.unwrap_or_else(|| DEFAULT_PATH.into());
+
+		for dir in env::split_paths(&path) {
+			let candidate = dir.join(&self.program);
+
+			if is_executable(&candidate) {
+				return Ok(CString::new(candidate.into_os_string().into_vec())?);
+			}
+		}
+
+		Err(OsError::NoEnt.into())
+	}
+
+	fn environment(&self) -> Result<Vec<CString>> {
+		let mut vars: BTreeMap<OsString, OsString> = if self.env_clear {
+			BTreeMap::new()
+		} else {
+			env::vars_os().collect()
+		};
+
+		for (key, value) in &self.env {
+			match value {
+				Some(value) => vars.insert(key.clone(), value.clone()),
+				None => vars.remove(key)
+			};
+		}
+
+		vars.into_iter()
+			.map(|(key, value)| {
+				let mut entry = key.into_vec();
+
+				entry.push(b'=');
+				entry.extend_from_slice(value.as_bytes());
+
+				Ok(CString::new(entry)?)
+			})
+			.collect()
+	}
+
+	#[allow(clippy::cast_sign_loss)]
+	fn spawn_with(&self, default_input: Stdio, default_output: Stdio) -> Result<Child> {
+		let path = self.resolve_program()?;
+		let args = self
+			.args
+			.iter()
+			.map(|arg| CString::new(arg.as_bytes()))
+			.collect::<std::result::Result<Vec<_>, _>>()?;
+		let env = self.environment()?;
+		let cwd = self
+			.cwd
+			.as_ref()
+			.map(|cwd| CString::new(cwd.as_os_str().as_bytes()))
+			.transpose()?;
+
+		let (argv, envp) = (null_terminated(&args), null_terminated(&env));
+
+		let (stdin, parent_stdin) = self.stdin.as_ref().unwrap_or(&default_input).open(true)?;
+		let (stdout, parent_stdout) = self
+			.stdout
+			.as_ref()
+			.unwrap_or(&default_output)
+			.open(false)?;
+		let (stderr, parent_stderr) = self
+			.stderr
+			.as_ref()
+			.unwrap_or(&default_output)
+			.open(false)?;
+
+		let parent_stdin = parent_stdin.map(ChildStdin::new).transpose()?;
+		let parent_stdout = parent_stdout.map(ChildStdout::new).transpose()?;
+		let parent_stderr = parent_stderr.map(ChildStderr::new).transpose()?;
+
+		let exec = Exec {
+			path: &path,
+			argv: ptr!(argv.as_ptr()),
+			envp: ptr!(envp.as_ptr()),
+			cwd: cwd.as_deref(),
+			stdio: [
+				stdin.as_ref().map(AsFd::as_fd),
+				stdout.as_ref().map(AsFd::as_fd),
+				stderr.as_ref().map(AsFd::as_fd)
+			],
+			error: Cell::new(None)
+		};
+
+		let stack = Builder::new(Type::Private, CHILD_STACK_SIZE)
+			.protect(Protection::Read | Protection::Write)
+			.flag(Flag::Anonymous | Flag::Stack)
+			.map()?;
+
+		/* Safety: the end of the mapping */
+		let stack_top = unsafe { stack.as_ptr().cast::<u8>().add(stack.len()) }.cast();
+
+		/* block every signal so that none of our handlers run in the child
+		 * before it resets them */
+		let mut mask = [0];
+
+		pthread_set_sigmask(SignalHow::SetMask, Some(&[SignalSet::MAX]), Some(&mut mask))?;
+
+		let mut pidfd = INVALID_FD;
+
+		/* Safety: we are suspended until the child calls exec or exits, so the
+		 * stack and `exec` outlive its use of them. the child only makes
+		 * syscalls before then */
+		let result = unsafe {
+			clone_on_stack(
+				start_child,
+				stack_top,
+				CloneFlag::Vm | CloneFlag::VFork | CloneFlag::PidFd,
+				Signal::Child,
+				ptr!(&exec).cast_mut().cast(),
+				ptr!(&mut pidfd)
+			)
+		};
+
+		pthread_set_sigmask(SignalHow::SetMask, Some(&mask), None)?;
+
+		let pid = result?;
+
+		/* Safety: the kernel returned a new pidfd */
+		let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd) };
+
+		/* the child has either called exec or exited */
+		if let Some(err) = exec.error.get() {
+			wait_pidfd(pidfd.as_fd(), BitFlags::default())?;
+
+			return Err(err.into());
+		}
+
+		Ok(Child {
+			pid,
+			pidfd,
+			status: None,
+			stdin: parent_stdin,
+			stdout: parent_stdout,
+			stderr: parent_stderr
+		})
+	}
+
+	/// Spawn the command as a child process
+	///
+	/// Streams that were not configured are inherited from the parent
+	pub fn spawn(&self) -> Result<Child> {
+		self.spawn_with(Stdio::inherit(), Stdio::inherit())
+	}
+
+	/// Spawn the command and wait for it to exit, collecting its output
+	///
+	/// Unless configured otherwise, stdout and stderr are piped, and stdin is
+	/// connected to `/dev/null`
+	pub async fn output(&self) -> Result<Output> {
+		let child = self.spawn_with(Stdio::null(), Stdio::piped())?;
+
+		child.wait_with_output().await
+	}
+
+	/// Spawn the command and wait for it to exit
+	///
+	/// Streams that were not configured are inherited from the parent
+	pub async fn status(&self) -> Result<ExitStatus> {
+		self.spawn()?.wait().await
+	}
+}
@@ -0,0 +1,255 @@
+//! Async signal handling, backed by a signalfd
+//!
+//! Creating a [`Signals`] stream blocks its signals on the calling thread, so
+//! that they are queued to the signalfd instead of being handled. Every other
+//! [`Executor`] blocks them the next time one of its workers suspends, and the
+//! [`run_blocking`] workers block all process directed signals.
+//!
+//! Threads that still have the signals unblocked, such as an idle executor or
+//! a thread started before the stream, run a handler instead, which forwards
+//! the signal to the most recently created stream accepting it through a
+//! pipe. The signal's previous action is restored once every stream
+//! accepting it is dropped.
+//!
+//! Waiting polls the signalfd and the pipe, so it does not occupy a
+//! [`run_blocking`] thread.
+//!
+//! Each signal is only delivered once. If multiple streams accept the same
+//! signal, only one of them receives it.
+//!
+//! [`Executor`]: crate::coroutines::Executor
+//! [`run_blocking`]: super::blocking::run_blocking
+
+use std::hint::spin_loop;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::io::wait_ready;
+use super::*;
+use crate::os::epoll::{self, ControlOp, Event, EventPoll};
+use crate::os::error::OsError;
+use crate::os::fcntl::OpenFlag;
+use crate::os::poll::PollFlag;
+use crate::os::signal::*;
+use crate::os::signalfd::{signal_bit, signal_set, CreateFlag, SignalFd};
+use crate::os::{unistd, INVALID_FD};
+use crate::pointer::*;
+use crate::runtime::block_runtime_signals;
+use crate::warn;
+
+/// The highest signal number
+const MAX_SIGNAL: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_STREAM: AtomicI32 = AtomicI32::new(INVALID_FD);
+
+/// The write end of the pipe that [`forward`] writes each signal to, indexed
+/// by signal number
+static FORWARD: [AtomicI32; MAX_SIGNAL + 1] = [NO_STREAM; MAX_SIGNAL + 1];
+
+/// The number of [`forward`] calls in progress, which must reach zero before
+/// a pipe is closed
+static FORWARDING: AtomicUsize = AtomicUsize::new(0);
+
+/// The streams accepting a signal, and the action to restore once they are
+/// all dropped
+struct Installed {
+	previous: SigAction,
+	pipes: Vec<RawFd>
+}
+
+static INSTALLED: Mutex<Vec<(i32, Installed)>> = Mutex::new(Vec::new());
+
+extern "C" fn forward(signal: i32, info: MutPtr<SigInfo>, _: MutPtr<()>) {
+	FORWARDING.fetch_add(1, Ordering::SeqCst);
+
+	#[allow(clippy::cast_sign_loss)]
+	let pipe = FORWARD
+		.get(signal as usize)
+		.map_or(INVALID_FD, |pipe| pipe.load(Ordering::SeqCst));
+
+	if pipe != INVALID_FD {
+		/* Safety: the pipe isn't closed until `FORWARDING` is zero */
+		let pipe = unsafe { BorrowedFd::borrow_raw(pipe) };
+
+		/* Safety: the kernel passes a valid siginfo */
+		let info = unsafe { info.as_ref() };
+
+		/* if the pipe is full, the signal is dropped, as a standard signal that
+		 * is already pending would be */
+		let _ = unistd::write(pipe, info.into());
+	}
+
+	FORWARDING.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn signals_in(mask: SignalSet) -> impl Iterator<Item = i32> {
+	#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+	(1..=MAX_SIGNAL as i32).filter(move |&signal| mask & signal_bit(signal) != 0)
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn install_forward(mask: SignalSet, pipe: RawFd) -> OsResult<()> {
+	#[allow(clippy::unwrap_used)]
+	let mut installed = INSTALLED.lock().unwrap();
+
+	for signal in signals_in(mask) {
+		match installed.iter_mut().find(|(num, _)| *num == signal) {
+			Some((_, entry)) => entry.pipes.push(pipe),
+			None => {
+				let mut action = SigAction::default();
+				let mut previous = SigAction::default();
+
+				action.handler.action = Some(forward);
+				action.flags = (SignalFlags::SigInfo | SignalFlags::Restart).bits();
+
+				sig_action(signal, Some(&action), Some(&mut previous))?;
+				installed.push((signal, Installed { previous, pipes: vec![pipe] }));
+			}
+		}
+
+		FORWARD[signal as usize].store(pipe, Ordering::SeqCst);
+	}
+
+	Ok(())
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn remove_forward(mask: SignalSet, pipe: RawFd) {
+	#[allow(clippy::unwrap_used)]
+	let mut installed = INSTALLED.lock().unwrap();
+
+	for signal in signals_in(mask) {
+		let Some(index) = installed.iter().position(|(num, _)| *num == signal) else {
+			continue;
+		};
+
+		let entry = &mut installed[index].1;
+
+		entry.pipes.retain(|fd| *fd != pipe);
+
+		let last = entry.pipes.last().copied().unwrap_or(INVALID_FD);
+
+		FORWARD[signal as usize].store(last, Ordering::SeqCst);
+
+		if last == INVALID_FD {
+			let (_, entry) = installed.swap_remove(index);
+
+			if let Err(err) = sig_action(signal, Some(&entry.previous), None) {
+				warn!("== Failed to restore signal {}: {:?}", signal, err);
+			}
+		}
+	}
+
+	/* the pipe may still be in use by a handler that loaded it before it was
+	 * replaced */
+	while FORWARDING.load(Ordering::SeqCst) != 0 {
+		spin_loop();
+	}
+}
+
+/// A stream of signals received by the process
+pub struct Signals {
+	fd: SignalFd,
+	mask: SignalSet,
+	poll: EventPoll,
+	reader: OwnedFd,
+	writer: OwnedFd
+}
+
+#[asynchronous]
+impl Signals {
+	/// Create a stream receiving any of `signals`
+	pub fn new(signals: &[Signal]) -> Result<Self> {
+		let mask = signal_set(signals);
+		let fd = SignalFd::new(mask, CreateFlag::CloseOnExec | CreateFlag::NonBlock)?;
+		let (reader, writer) = unistd::pipe2(OpenFlag::CloseOnExec | OpenFlag::NonBlock)?;
+		let poll = EventPoll::new(epoll::CreateFlag::CloseOnExec.into())?;
+
+		for fd in [fd.fd(), reader.as_fd()] {
+			let mut event = Event { events: PollFlag::In as u32, data: 0 };
+
+			poll.ctl(ControlOp::Add, fd, &mut event)?;
+		}
+
+		/* threads that haven't blocked the signals yet forward them instead of
+		 * taking the default action */
+		if let Err(err) = install_forward(mask, writer.as_raw_fd()) {
+			remove_forward(mask, writer.as_raw_fd());
+
+			return Err(err.into());
+		}
+
+		let this = Self { fd, mask, poll, reader, writer };
+
+		block_runtime_signals(mask)?;
+
+		Ok(this)
+	}
+
+	/// The set of signals accepted by this stream
+	#[must_use]
+	pub const fn mask(&self) -> SignalSet {
+		self.mask
+	}
+
+	/// Wait for the next signal
+	///
+	/// # Cancel safety
+	///
+	/// This function is cancel safe. Once the interrupt is cleared, call this
+	/// function again to resume the operation.
+	pub async fn recv(&mut self) -> Result<SigInfo> {
+		loop {
+			match self.fd.read() {
+				Ok(info) => return Ok(info.into()),
+				Err(OsError::Again) => (),
+				Err(err) => return Err(err.into())
+			}
+
+			let mut info = SigInfo::default();
+
+			match unistd::read(self.reader.as_fd(), (&mut info).into()) {
+				Ok(_) => return Ok(info),
+				Err(OsError::Again) => (),
+				Err(err) => return Err(err.into())
+			}
+
+			/* the epoll fd is readable once either the signalfd or the pipe is */
+			wait_ready(self.poll.fd(), PollFlag::In.into()).await?;
+		}
+	}
+}
+
+impl Drop for Signals {
+	fn drop(&mut self) {
+		remove_forward(self.mask, self.writer.as_raw_fd());
+	}
+}
+
+#[asynchronous]
+impl AsyncIterator for Signals {
+	type Item = Result<SigInfo>;
+
+	/// Returns the next signal, or the error from waiting for it. The stream
+	/// never ends
+	async fn next(&mut self) -> Option<Result<SigInfo>> {
+		Some(self.recv().await)
+	}
+}
+
+/// Create a stream receiving `signal`
+///
+/// # Examples
+///
+/// ```
+/// let mut terminate = signal(Signal::Termination)?;
+///
+/// terminate.recv().await?;
+///
+/// // shut down gracefully
+/// ```
+pub fn signal(signal: Signal) -> Result<Signals> {
+	Signals::new(&[signal])
+}
@@ -321,6 +321,12 @@ macro_rules! channel_impl {
 				loop {
 					let result = self.try_recv();
 
+					if result.is_ok() {
+						consume_budget().await;
+
+						return result;
+					}
+
 					if !matches!(result, Err(RecvError::Empty)) || is_interrupted().await {
 						return result;
 					}
@@ -441,7 +447,12 @@ macro_rules! channel_impl {
 
 				loop {
 					match self.try_send(value) {
-						Ok(()) => return Ok(()),
+						Ok(()) => {
+							consume_budget().await;
+
+							return Ok(());
+						}
+
 						Err(err @ SendError::Closed(_)) => return Err(err),
 						result if is_interrupted().await => return result,
 						Err(SendError::Full(v)) => value = v
@@ -0,0 +1,84 @@
+//! Async waits on a futex word shared with plain threads or other processes
+//!
+//! The wait is performed on the blocking thread pool, suspending the calling
+//! task instead of the thread. Futexes are shared (not process private), so
+//! the word may live in memory mapped by multiple processes.
+//!
+//! The ring has [`OpCode::FutexWait`], but there is no ring driver in this
+//! crate to submit it to, so every wait uses the pool. A waiting task holds a
+//! pool thread until it is woken, times out or is interrupted, with the costs
+//! described in [`run_blocking`]. Use a timeout, or the crate's own sync
+//! primitives, for waits that only involve tasks.
+//!
+//! [`OpCode::FutexWait`]: crate::os::io_uring::OpCode::FutexWait
+//! [`run_blocking`]: crate::async_std::blocking::run_blocking
+
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+
+use super::*;
+use crate::async_std::blocking::run_blocking;
+use crate::os::error::OsError;
+use crate::os::futex;
+use crate::os::time::TimeSpec;
+
+/// Suspend until `word` is woken by [`futex_wake`], if it still contains
+/// `expected`
+///
+/// Returns `false` if `word` did not contain `expected`. Like all futex waits,
+/// wakeups may be spurious, so the caller should check the state again.
+///
+/// Returns an [`Interrupted`] error if the task is interrupted while waiting
+///
+/// [`Interrupted`]: ErrorKind::Interrupted
+#[asynchronous]
+pub async fn futex_wait(word: &AtomicU32, expected: u32) -> Result<bool> {
+	futex_wait_inner(word, expected, None)
+		.await
+		.map(|woken| woken.unwrap_or(true))
+}
+
+/// Same as [`futex_wait`], except that it gives up after `timeout`
+///
+/// Returns `None` on timeout
+#[asynchronous]
+pub async fn futex_wait_timeout(
+	word: &AtomicU32, expected: u32, timeout: Duration
+) -> Result<Option<bool>> {
+	futex_wait_inner(word, expected, Some(timeout)).await
+}
+
+#[asynchronous]
+async fn futex_wait_inner(
+	word: &AtomicU32, expected: u32, timeout: Option<Duration>
+) -> Result<Option<bool>> {
+	if word.load(Ordering::Acquire) != expected {
+		return Ok(Some(false));
+	}
+
+	let timeout = timeout.map(TimeSpec::from_duration);
+	let result =
+		run_blocking(|_| futex::futex_wait(word, expected, timeout.as_ref(), false)).await?;
+
+	match result {
+		Ok(()) => Ok(Some(true)),
+		Err(OsError::Again) => Ok(Some(false)),
+		Err(OsError::TimedOut) => Ok(None),
+		Err(OsError::Intr) => Err(interrupt_error().await),
+		Err(err) => Err(err.into())
+	}
+}
+
+/// Wake up to `count` tasks or threads waiting on `word`, returning the
+/// number woken
+///
+/// Waking never blocks, so this function does not suspend
+pub fn futex_wake(word: &AtomicU32, count: u32) -> Result<u32> {
+	Ok(futex::futex_wake(word, count, false)?)
+}
+
+/// Wake all waiters on `word`, returning the number woken
+pub fn futex_wake_all(word: &AtomicU32) -> Result<u32> {
+	#[allow(clippy::cast_sign_loss)]
+	futex_wake(word, i32::MAX as u32)
+}
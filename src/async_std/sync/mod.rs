@@ -12,6 +12,8 @@
 //!
 //! [`broadcast`]: broadcast all sent values to every receiver
 //!
+//! [`futex`]: wait on memory shared with threads or other processes
+//!
 //! [`mutex`]: mutual exclusion
 //!
 //! [`notify`]: a simple wait list
@@ -30,6 +32,7 @@ use self::wait_list::*;
 
 pub mod broadcast;
 pub mod channel;
+pub mod futex;
 pub mod mutex;
 pub mod notify;
 
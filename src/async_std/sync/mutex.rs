@@ -174,6 +174,10 @@ impl<T: ?Sized> Mutex<T> {
 	/// [`try_lock`]: Self::try_lock
 	/// [`WouldBlock`]: TryLockError::WouldBlock
 	pub async fn lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+		/* yield before taking the lock, so that we don't suspend while holding
+		 * it */
+		consume_budget().await;
+
 		if !self.try_lock_internal() {
 			let locked = self.lock_contended().await;
 
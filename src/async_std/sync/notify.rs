@@ -39,7 +39,10 @@ pub struct RawNotify<T = ()> {
 impl<T: Clone> RawNotify<T> {
 	#[asynchronous]
 	pub async fn wait(&self) -> Result<T> {
-		self.waiters.wait().await.map_err(Into::into)
+		match self.waiters.wait().await {
+			Ok(value) => Ok(value),
+			Err(err) => Err(wait_error(err).await)
+		}
 	}
 
 	pub fn notify(&self, value: T) -> usize {
@@ -121,7 +124,10 @@ impl<T: Clone> Notify<T> {
 
 	#[asynchronous]
 	pub async fn wait(&self) -> Result<T> {
-		self.waiters.wait(|| true).await.map_err(Into::into)
+		match self.waiters.wait(|| true).await {
+			Ok(value) => Ok(value),
+			Err(err) => Err(wait_error(err).await)
+		}
 	}
 
 	pub fn notify(&self, value: T) -> usize {
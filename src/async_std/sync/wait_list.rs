@@ -31,6 +31,16 @@ async fn check_interrupt() -> WaitResult<()> {
 		.map_err(|_| WaitError::Cancelled)
 }
 
+/// Converts a [`WaitError`] into an [`Error`]. A cancelled wait reports the
+/// reason the task was interrupted
+#[asynchronous]
+pub async fn wait_error(err: WaitError) -> Error {
+	match err {
+		WaitError::Cancelled => coroutines::interrupt_error().await,
+		err => err.into()
+	}
+}
+
 const fn closed<T>() -> ReqPtr<T> {
 	Ptr::from_addr(usize::MAX)
 }
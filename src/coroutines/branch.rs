@@ -434,3 +434,263 @@ where
 	/* Safety: branch is pinned. we are blocked until the future completes */
 	block_on(unsafe { Branch::run(ptr!(&mut *branch.pin_local())) }).await
 }
+
+/// The output of [`branch_all`]
+///
+/// The first field is the index of the future whose output caused the others
+/// to be cancelled, if any. The second holds the result of each future in
+/// order, or `None` for futures that were skipped because they hadn't
+/// started yet
+pub struct BranchAllOutput<O>(pub Option<usize>, pub Vec<Option<MaybePanic<O>>>);
+
+impl<O> BranchAllOutput<MaybePanic<O>> {
+	pub fn flatten(self) -> BranchAllOutput<O> {
+		let results = self
+			.1
+			.into_iter()
+			.map(|result| result.map(|result| result.and_then(|result| result)))
+			.collect();
+
+		BranchAllOutput(self.0, results)
+	}
+}
+
+/// A branch over any number of futures of the same type, which share a single
+/// allocation
+pub struct BranchAll<F: Future, Cancel> {
+	handles: Box<[FutureHandle<F>]>,
+	request: ReqPtr<BranchAllOutput<F::Output>>,
+	should_cancel: Cancel,
+
+	/* the number of futures that were started and haven't completed */
+	pending: Cell<usize>,
+
+	/* the index of the future that caused the others to be cancelled */
+	first: Cell<Option<usize>>,
+
+	/* set while starting or cancelling futures, which prevents the branch from
+	 * completing before the loop ends */
+	busy: Cell<bool>
+}
+
+impl<F: Future, C: Fn(&MaybePanic<F::Output>) -> bool> BranchAll<F, C> {
+	fn index_of(&self, request: ReqPtr<F::Output>) -> usize {
+		/* Safety: the request belongs to one of the handles, so there is at least
+		 * one */
+		let first = unsafe { self.handles.get_unchecked(0) };
+		let offset = request.addr().wrapping_sub(ptr!(&first.request).addr());
+
+		#[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+		(offset / size_of::<FutureHandle<F>>())
+	}
+
+	/// # Safety
+	/// must only be called when the future at `index` has completed
+	unsafe fn done_single(this: MutPtr<Self>, index: usize) {
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this.as_mut() };
+
+		#[allow(clippy::arithmetic_side_effects)]
+		this.pending.update(|pending| pending - 1);
+
+		/* Safety: guaranteed by caller */
+		let result = match unsafe { &this.handles.get_unchecked(index).state } {
+			State::Done(result) => result,
+			/* Safety: the future has completed */
+			_ => unsafe { unreachable_unchecked!("Future is still in progress") }
+		};
+
+		if this.first.get().is_some() || !call_no_unwind(|| (this.should_cancel)(result)) {
+			return;
+		}
+
+		this.first.set(Some(index));
+
+		/* Safety: `this` is valid */
+		let _ = unsafe { Self::cancel_all(ptr!(this)) };
+	}
+
+	/// # Safety
+	/// `this` must be valid, and may be dangling after this call
+	unsafe fn try_complete(this: MutPtr<Self>) {
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this.as_mut() };
+
+		if this.busy.get() || this.pending != 0 {
+			return;
+		}
+
+		let output = this.output();
+
+		/*
+		 * Safety: complete the future. we must not access `self` once a cancel or a
+		 * complete is called, as we may be freed by the callee
+		 */
+		unsafe { Request::complete(this.request, output) };
+	}
+
+	fn output(&mut self) -> BranchAllOutput<F::Output> {
+		let mut results = Vec::with_capacity(self.handles.len());
+
+		for handle in &mut *self.handles {
+			let result = if handle.done() {
+				/* Safety: the future completed */
+				Some(unsafe { handle.result() })
+			} else {
+				None
+			};
+
+			results.push(result);
+		}
+
+		BranchAllOutput(self.first.get(), results)
+	}
+
+	/// # Safety
+	/// must only be called when a future completes
+	unsafe fn complete_one(request: ReqPtr<F::Output>, arg: Ptr<()>, value: F::Output) {
+		let this = arg.cast::<Self>().cast_mut();
+
+		/* Safety: guaranteed by caller */
+		unsafe {
+			let index = ptr!(this=>index_of(request));
+
+			ptr!(this=>handles.get_unchecked_mut(index).complete(Ok(value)));
+
+			Self::done_single(this, index);
+			Self::try_complete(this);
+		}
+	}
+
+	pub fn new<I>(futures: I, should_cancel: C) -> Self
+	where
+		I: IntoIterator<Item = F>
+	{
+		let handles = futures
+			.into_iter()
+			.map(|future| {
+				/* Safety: complete does not unwind */
+				unsafe { FutureHandle::new(future, Self::complete_one) }
+			})
+			.collect();
+
+		/* request args are assigned once pinned */
+		Self {
+			handles,
+			request: Ptr::null(),
+			should_cancel,
+			pending: Cell::new(0),
+			first: Cell::new(None),
+			busy: Cell::new(false)
+		}
+	}
+
+	/// # Safety
+	/// See [`Cancel::run`]
+	/// The caller must check if the branch completed afterwards
+	unsafe fn cancel_all(this: MutPtr<Self>) -> Result<()> {
+		/* Safety: guaranteed by future's contract */
+		let this = unsafe { this.as_mut() };
+		let busy = this.busy.replace(true);
+		let mut result = Ok(());
+
+		/* cancelling may complete futures synchronously, but the branch
+		 * can't complete until `busy` is reset
+		 */
+		for index in 0..this.handles.len() {
+			/* Safety: index is in bounds */
+			let Some(cancel) = (unsafe { this.handles.get_unchecked_mut(index) }).take_cancel()
+			else {
+				continue;
+			};
+
+			/* Safety: the future is in progress */
+			let cancel_result = runtime::join(unsafe { run_cancel(cancel) });
+
+			if result.is_ok() {
+				result = cancel_result;
+			}
+		}
+
+		this.busy.set(busy);
+
+		result
+	}
+
+	/// # Safety
+	/// see `Future::run`
+	/// self must be pinned
+	/// `this` must be a valid pointer
+	#[future]
+	pub unsafe fn run(this_ptr: MutPtr<Self>, request: _) -> BranchAllOutput<F::Output> {
+		#[cancel]
+		fn cancel(self: MutPtr<Self>) -> Result<()> {
+			/* Safety: caller must uphold Future's contract */
+			let result = unsafe { Self::cancel_all(self) };
+
+			/* Safety: the branch may have completed */
+			unsafe { Self::try_complete(self) };
+
+			result
+		}
+
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this_ptr.as_mut() };
+
+		this.request = request;
+		this.busy.set(true);
+
+		for index in 0..this.handles.len() {
+			if this.first.get().is_some() {
+				/* the remaining futures are never started */
+				break;
+			}
+
+			#[allow(clippy::arithmetic_side_effects)]
+			this.pending.update(|pending| pending + 1);
+
+			/* Safety: caller must uphold Future's contract. index is in bounds */
+			if unsafe { this.handles.get_unchecked_mut(index).run() }.is_some() {
+				/* Safety: the future completed synchronously */
+				unsafe { Self::done_single(this_ptr, index) };
+			}
+		}
+
+		this.busy.set(false);
+
+		if this.pending == 0 {
+			Progress::Done(this.output())
+		} else {
+			Progress::Pending(cancel(this_ptr))
+		}
+	}
+}
+
+impl<F: Future, Cancel> Pin for BranchAll<F, Cancel> {
+	unsafe fn pin(&mut self) {
+		let arg = ptr!(&*self).cast();
+
+		for handle in &mut *self.handles {
+			handle.set_arg(arg);
+		}
+	}
+}
+
+/// Runs every future in `futures`. Once a future completes with an output for
+/// which `should_cancel` returns true, the futures that haven't completed are
+/// cancelled, and the ones that haven't started are skipped
+///
+/// # Safety
+/// `should_cancel` must not unwind
+#[asynchronous]
+pub async unsafe fn branch_all<I, F, C>(futures: I, should_cancel: C) -> BranchAllOutput<F::Output>
+where
+	I: IntoIterator<Item = F>,
+	F: Future,
+	C: Fn(&MaybePanic<F::Output>) -> bool
+{
+	let mut branch = BranchAll::new(futures, should_cancel);
+
+	/* Safety: branch is pinned. we are blocked until the future completes */
+	block_on(unsafe { BranchAll::run(ptr!(&mut *branch.pin_local())) }).await
+}
@@ -0,0 +1,247 @@
+//! Cancellation shared between tasks
+//!
+//! A [`CancellationToken`] is cancelled once, and stays cancelled. Tasks can
+//! wait for it with [`CancellationToken::cancelled`], or bind themselves to it
+//! with [`CancellationToken::bind`] so that cancelling the token interrupts
+//! them. Cancelling a token also cancels every token created from it with
+//! [`CancellationToken::child_token`], so a single shutdown signal can tear
+//! down a whole tree of tasks
+//!
+//! ```
+//! let shutdown = CancellationToken::new();
+//!
+//! for conn in connections {
+//! 	let token = shutdown.child_token();
+//!
+//! 	spawn(async move { token.bind(handle_connection(conn)).await }).await;
+//! }
+//!
+//! signal(Signal::Terminate)?.next().await;
+//! shutdown.cancel();
+//! ```
+
+use std::mem::take;
+use std::rc::{Rc, Weak};
+
+use super::*;
+use crate::async_std::sync::RcNotify;
+use crate::cell::{Cell, UnsafeCell};
+
+struct TokenState {
+	cancelled: Cell<bool>,
+	notify: RcNotify,
+	children: UnsafeCell<Vec<Weak<TokenState>>>,
+
+	/* the tasks bound to this token */
+	bound: UnsafeCell<Vec<Ptr<Binding>>>
+}
+
+impl TokenState {
+	fn new(cancelled: bool) -> Self {
+		Self {
+			cancelled: Cell::new(cancelled),
+			notify: RcNotify::new(),
+			children: UnsafeCell::new(Vec::new()),
+			bound: UnsafeCell::new(Vec::new())
+		}
+	}
+
+	fn add_child(&self, child: &Rc<Self>) {
+		/* Safety: exclusive unsafe cell access */
+		let children = unsafe { self.children.as_mut() };
+
+		if children.len() == children.capacity() {
+			/* drop tokens that no longer exist before growing the list */
+			children.retain(|child| child.strong_count() != 0);
+		}
+
+		children.push(Rc::downgrade(child));
+	}
+
+	fn bind(&self, binding: Ptr<Binding>) {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.bound.as_mut() }.push(binding);
+	}
+
+	fn unbind(&self, binding: Ptr<Binding>) {
+		/* Safety: exclusive unsafe cell access */
+		let bound = unsafe { self.bound.as_mut() };
+
+		/* the binding may have already been removed by `cancel` */
+		if let Some(index) = bound.iter().rposition(|bound| *bound == binding) {
+			bound.swap_remove(index);
+		}
+	}
+
+	fn pop_bound(&self) -> Option<Ptr<Binding>> {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.bound.as_mut() }.pop()
+	}
+
+	fn cancel(&self) {
+		if self.cancelled.replace(true) {
+			return;
+		}
+
+		self.notify.notify(());
+
+		/* Safety: exclusive unsafe cell access */
+		let children = take(unsafe { self.children.as_mut() });
+
+		for child in children.iter().filter_map(Weak::upgrade) {
+			child.cancel();
+		}
+
+		/* interrupting a task may resume it, and it may bind or unbind any
+		 * token before returning here, so take them one at a time
+		 */
+		while let Some(binding) = self.pop_bound() {
+			/* Safety: bindings are alive until they are unbound */
+			if let Err(err) = unsafe { Binding::interrupt(binding) } {
+				debug!(target: self, ">> Interrupt failed: {:?}", err);
+			}
+		}
+	}
+}
+
+/// A task bound to a token
+struct Binding {
+	context: Ptr<Context>,
+
+	/* set if the token interrupted the task, rather than something else */
+	interrupted: Cell<bool>
+}
+
+impl Binding {
+	/// # Safety
+	/// `this` must be valid, and may be dangling after this call. See
+	/// `Context::interrupt`
+	unsafe fn interrupt(this: Ptr<Self>) -> Result<()> {
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this.as_ref() };
+		let context = this.context;
+
+		/* Safety: the bound task is alive */
+		if !unsafe { ptr!(context=>interrupt_pending()) } {
+			this.interrupted.set(true);
+		}
+
+		/* Safety: guaranteed by caller. `this` is not used after */
+		unsafe { Context::interrupt(context) }
+	}
+}
+
+struct BindGuard<'a> {
+	state: &'a TokenState,
+	binding: Binding
+}
+
+impl Drop for BindGuard<'_> {
+	fn drop(&mut self) {
+		self.state.unbind(ptr!(&self.binding));
+
+		if self.binding.interrupted.get() {
+			/* Safety: the bound task is still running */
+			unsafe { ptr!(self.binding.context=>clear_interrupt()) };
+		}
+	}
+}
+
+/// A token for signalling cancellation to any number of tasks
+///
+/// Clones of a token share the same state. See [the module
+/// documentation](`self`) for more information
+#[derive(Clone)]
+pub struct CancellationToken {
+	state: Rc<TokenState>
+}
+
+#[asynchronous]
+impl CancellationToken {
+	/// Creates a new token that isn't cancelled
+	#[must_use]
+	pub fn new() -> Self {
+		Self { state: Rc::new(TokenState::new(false)) }
+	}
+
+	/// Creates a token that is cancelled when this one is
+	///
+	/// Cancelling the child does not cancel this token. If this token is
+	/// already cancelled, the child starts out cancelled
+	#[must_use]
+	pub fn child_token(&self) -> Self {
+		let state = Rc::new(TokenState::new(self.is_cancelled()));
+
+		if !self.is_cancelled() {
+			self.state.add_child(&state);
+		}
+
+		Self { state }
+	}
+
+	/// Cancels this token and all of its children, waking tasks waiting on
+	/// [`CancellationToken::cancelled`] and interrupting tasks bound to it
+	///
+	/// Cancelling an already cancelled token does nothing
+	pub fn cancel(&self) {
+		self.state.cancel();
+	}
+
+	/// Returns true if the token has been cancelled
+	#[must_use]
+	pub fn is_cancelled(&self) -> bool {
+		self.state.cancelled.get()
+	}
+
+	/// Waits until the token is cancelled
+	///
+	/// # Errors
+	/// If the current task is interrupted before the token is cancelled
+	///
+	/// # Cancel safety
+	///
+	/// This function is cancel safe. Once the interrupt is cleared, call this
+	/// function again to resume the operation.
+	pub async fn cancelled(&self) -> Result<()> {
+		if self.is_cancelled() {
+			return Ok(());
+		}
+
+		self.state.notify.wait().await
+	}
+
+	/// Runs `task` on the current worker, interrupting it if this token is
+	/// cancelled before it completes
+	///
+	/// If the token is already cancelled, the task starts out interrupted. An
+	/// interrupt caused by this token is cleared once the task completes, so
+	/// that the caller can continue
+	pub async fn bind<T, Output>(&self, task: T) -> Output
+	where
+		T: for<'ctx> Task<Output<'ctx> = Output>
+	{
+		let context = get_context().await;
+		let binding = Binding {
+			context: ptr!(context),
+			interrupted: Cell::new(false)
+		};
+		let guard = BindGuard { state: &self.state, binding };
+		let binding = ptr!(&guard.binding);
+
+		if self.is_cancelled() {
+			/* Safety: we are running, so this only sets the interrupt */
+			let _ = unsafe { Binding::interrupt(binding) };
+		} else {
+			self.state.bind(binding);
+		}
+
+		/* Safety: we are in an async function */
+		unsafe { scoped(context, task) }
+	}
+}
+
+impl Default for CancellationToken {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -1,8 +1,10 @@
 #![allow(clippy::multiple_unsafe_ops_per_block)]
 
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 use std::hash::{DefaultHasher, Hash, Hasher};
 
+use super::interrupt::reason_error;
+use super::local::{Locals, Slot};
 use super::*;
 use crate::cell::*;
 use crate::closure::*;
@@ -41,7 +43,12 @@ struct Data {
 	budget: Cell<u16>,
 	guards: Cell<u32>,
 	interrupted: Cell<bool>,
-	cancel: UnsafeCell<Option<Canceller>>
+	reason: UnsafeCell<Option<InterruptReason>>,
+
+	/* where the task's spawner leaves the reason for its next interrupt */
+	reason_source: Cell<Ptr<UnsafeCell<Option<InterruptReason>>>>,
+	cancel: UnsafeCell<Option<Canceller>>,
+	locals: UnsafeCell<Locals>
 }
 
 impl Data {
@@ -53,7 +60,10 @@ impl Data {
 			budget: Cell::new(DEFAULT_BUDGET as u16),
 			guards: Cell::new(0),
 			interrupted: Cell::new(false),
-			cancel: UnsafeCell::new(None)
+			reason: UnsafeCell::new(None),
+			reason_source: Cell::new(Ptr::null()),
+			cancel: UnsafeCell::new(None),
+			locals: UnsafeCell::new(Locals::new())
 		}
 	}
 }
@@ -63,7 +73,8 @@ pub struct Context {
 	environment: u32,
 	worker: Ptr<Worker>,
 	waker: Option<Waker>,
-	data: Data
+	data: Data,
+	task: TaskInfo
 }
 
 impl Context {
@@ -84,8 +95,14 @@ impl Context {
 	/// # Safety
 	/// same as Worker::suspend
 	pub(super) unsafe fn suspend(&self) {
+		if self.task.state() == TaskState::Running {
+			self.task.set_state(TaskState::Suspended);
+		}
+
 		/* Safety: guaranteed by caller */
 		unsafe { ptr!(self.worker=>suspend()) };
+
+		self.task.set_state(TaskState::Running);
 	}
 
 	/// # Safety
@@ -120,8 +137,8 @@ impl Context {
 				unsafe { waker.prepare() };
 			}
 
-			#[allow(clippy::cast_possible_truncation)]
-			self.data.budget.set(DEFAULT_BUDGET as u16);
+			self.reset_budget();
+			self.task.set_state(TaskState::Blocked(type_name::<F>()));
 
 			/* Safety: context is valid while executing */
 			unsafe {
@@ -155,10 +172,21 @@ impl Context {
 		}
 	}
 
+	/// The waker for completing requests from other threads, if the runtime
+	/// supports it
+	pub(super) const fn waker(&self) -> Option<&Waker> {
+		self.waker.as_ref()
+	}
+
 	pub(super) fn current_budget(&self) -> u16 {
 		self.data.budget.get()
 	}
 
+	pub(super) fn reset_budget(&self) {
+		#[allow(clippy::cast_possible_truncation)]
+		self.data.budget.set(DEFAULT_BUDGET as u16);
+	}
+
 	pub(super) fn decrease_budget(&self, amount: u16) -> Option<u16> {
 		let result = self.data.budget.get().checked_sub(amount);
 
@@ -174,10 +202,70 @@ impl Context {
 		self.data.guards == 0 && self.data.interrupted.get()
 	}
 
+	/// Returns true if the worker is interrupted, or will be once its
+	/// interrupt guards are dropped
+	pub(super) fn interrupt_pending(&self) -> bool {
+		self.data.interrupted.get()
+	}
+
 	/// Clears any interrupts or pending interrupts (due to guards) on the
 	/// current worker
 	pub(super) fn clear_interrupt(&self) {
 		self.data.interrupted.set(false);
+
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.data.reason.as_mut() }.take();
+	}
+
+	/// Clears the interrupt if the worker is being interrupted, returning its
+	/// reason
+	pub(super) fn take_interrupt(&self) -> Option<InterruptReason> {
+		if !self.interrupted() {
+			return None;
+		}
+
+		self.data.interrupted.set(false);
+
+		/* Safety: exclusive unsafe cell access */
+		Some(
+			unsafe { self.data.reason.as_mut() }
+				.take()
+				.unwrap_or_default()
+		)
+	}
+
+	/// The error to return from an operation that failed because the worker
+	/// is interrupted
+	pub(super) fn interrupt_error(&self) -> Error {
+		/* Safety: exclusive unsafe cell access */
+		reason_error(unsafe { self.data.reason.as_ref() }.as_ref())
+	}
+
+	/// Returns a pointer to the task-local value in slot `index`, or null if
+	/// it isn't set
+	pub(super) fn local(&self, index: usize) -> Ptr<()> {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.data.locals.as_ref() }.get(index)
+	}
+
+	/// Set the task-local value in slot `index`, returning the previous one
+	pub(super) fn replace_local(&self, index: usize, slot: Slot) -> Slot {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.data.locals.as_mut() }.replace(index, slot)
+	}
+
+	/// Copy the task-local values that are inherited from `parent`
+	///
+	/// # Safety
+	/// the values in `parent` must be alive
+	pub(super) unsafe fn inherit_locals(&mut self, parent: &Self) {
+		/* Safety: guaranteed by caller */
+		unsafe {
+			self.data
+				.locals
+				.get_mut()
+				.inherit(parent.data.locals.as_ref());
+		}
 	}
 
 	/// # Safety
@@ -195,7 +283,8 @@ impl Context {
 			environment: type_for::<E>(),
 			worker: Ptr::null(),
 			waker,
-			data: Data::new()
+			data: Data::new(),
+			task: TaskInfo::new()
 		}
 	}
 
@@ -205,14 +294,66 @@ impl Context {
 		self.worker = worker;
 	}
 
+	/// Take the reason for the next interrupt from `source`, if none is
+	/// given. The task's spawner leaves it there before cancelling the task
+	///
+	/// # Safety
+	/// `source` must be valid for as long as the task can be interrupted
+	pub(super) unsafe fn set_reason_source(
+		&mut self, source: Ptr<UnsafeCell<Option<InterruptReason>>>
+	) {
+		self.data.reason_source.set(source);
+	}
+
+	/// The identity and state of the task running on this context
+	#[must_use]
+	pub const fn task(&self) -> &TaskInfo {
+		&self.task
+	}
+
+	pub(super) fn task_mut(&mut self) -> &mut TaskInfo {
+		&mut self.task
+	}
+
+	/// The executor running this context
+	pub(super) fn executor(&self) -> Ptr<Executor> {
+		/* Safety: the worker outlives this context */
+		unsafe { ptr!(self.worker=>executor()) }
+	}
+
 	/// Signals an interrupt to the current task
 	///
 	/// # Safety
 	/// See `Cancel::run`
 	pub unsafe fn interrupt(this: Ptr<Self>) -> Result<()> {
+		/* Safety: guaranteed by caller */
+		unsafe { Self::interrupt_with(this, None) }
+	}
+
+	/// Same as [`Context::interrupt`], with a reason that the task sees in
+	/// the errors caused by the interrupt
+	///
+	/// If the task is already interrupted, the reason it was first
+	/// interrupted with is kept
+	///
+	/// # Safety
+	/// See `Cancel::run`
+	pub unsafe fn interrupt_with(this: Ptr<Self>, reason: Option<InterruptReason>) -> Result<()> {
 		/* Safety: guaranteed by caller */
 		let this = unsafe { this.as_ref() };
 		let interrupted = this.data.interrupted.replace(true);
+		let source = this.data.reason_source.get();
+
+		/* Safety: the source is valid while the task runs. exclusive unsafe cell
+		 * access */
+		let pending = (!source.is_null())
+			.then(|| unsafe { ptr!(source=>as_mut()).take() })
+			.flatten();
+
+		if !interrupted {
+			/* Safety: exclusive unsafe cell access */
+			*unsafe { this.data.reason.as_mut() } = reason.or(pending);
+		}
 
 		#[allow(clippy::never_loop)]
 		loop {
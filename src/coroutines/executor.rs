@@ -1,14 +1,20 @@
 #![allow(clippy::multiple_unsafe_ops_per_block)]
 
+use super::registry::Registry;
 use super::*;
 use crate::cell::Cell;
+use crate::os::signal::*;
 
 /// Per thread executor, responsible for running worker threads
 #[cfg_attr(not(any(doc, feature = "xx-doc")), repr(C))]
 pub struct Executor {
 	current: Cell<Ptr<Worker>>,
 	main: Worker,
-	pool: Ptr<Pool>
+	pool: Option<LocalPool>,
+	tasks: Registry,
+
+	/* the runtime signals blocked on this thread */
+	signals: Cell<SignalSet>
 }
 
 impl Executor {
@@ -19,38 +25,110 @@ impl Executor {
 		unsafe { Self::new_with_pool(Ptr::null()) }
 	}
 
+	/// Create an executor that allocates workers through a per thread cache
+	/// in front of `pool`, or directly if `pool` is null
+	///
 	/// # Safety
 	/// `pool` must be either valid for this executor or null
 	#[must_use]
 	pub unsafe fn new_with_pool(pool: Ptr<Pool>) -> Self {
-		Self {
-			pool,
+		let this = Self {
+			/* Safety: guaranteed by caller */
+			pool: (!pool.is_null()).then(|| unsafe { LocalPool::new(pool) }),
 			main: Worker::main(),
+			tasks: Registry::new(),
+			signals: Cell::new(0),
 
 			/* current is assigned once pinned */
 			current: Cell::new(Ptr::null())
+		};
+
+		this.block_runtime_signals();
+		this
+	}
+
+	/// Block the signals added with [`runtime::block_runtime_signals`] since
+	/// the last check, so that signal streams created on other threads
+	/// receive them instead of this thread
+	fn block_runtime_signals(&self) {
+		let signals = runtime::runtime_signals();
+
+		if signals == self.signals.get() {
+			return;
+		}
+
+		self.signals.set(signals);
+
+		if let Err(err) = pthread_set_sigmask(SignalHow::Block, Some(&[signals]), None) {
+			warn!(target: self, "== Failed to block runtime signals: {:?}", err);
 		}
 	}
 
 	/// # Safety
 	/// `pool` must be either valid for this executor or null
 	pub unsafe fn set_pool(&mut self, pool: Ptr<Pool>) {
-		self.pool = pool;
+		/* Safety: guaranteed by caller */
+		self.pool = (!pool.is_null()).then(|| unsafe { LocalPool::new(pool) });
+	}
+
+	/// The per thread cache in front of this executor's pool, if any
+	#[must_use]
+	pub const fn local_pool(&self) -> Option<&LocalPool> {
+		self.pool.as_ref()
+	}
+
+	/// The number of spawned tasks running on this executor
+	#[must_use]
+	pub fn task_count(&self) -> usize {
+		self.tasks.len()
+	}
+
+	/// List every spawned task running on this executor, one per line
+	#[must_use]
+	pub fn dump_tasks(&self) -> String {
+		self.tasks.dump()
+	}
+
+	/// # Safety
+	/// `task` must be alive until it's unregistered
+	pub(super) unsafe fn register(&self, task: Ptr<TaskInfo>) {
+		/* Safety: guaranteed by caller */
+		unsafe { self.tasks.insert(task) };
+	}
+
+	/// # Safety
+	/// `task` must be registered with this executor
+	pub(super) unsafe fn unregister(&self, task: Ptr<TaskInfo>) {
+		/* Safety: guaranteed by caller */
+		unsafe { self.tasks.remove(task) };
 	}
 
 	/// # Safety
 	/// Executor must outlive the worker
 	pub unsafe fn new_worker(&self, start: Start) -> Worker {
-		if self.pool.is_null() {
-			/* Safety: guaranteed by caller */
-			unsafe { Worker::new(ptr!(self), start) }
-		} else {
-			/* Safety: pool must be valid for this executor */
-			let fiber = unsafe { ptr!(self.pool=>new_fiber(start)) };
+		/* Safety: guaranteed by caller */
+		unsafe { self.new_worker_with_hint(start, StackHint::Default) }
+	}
 
-			/* Safety: guaranteed by caller */
-			unsafe { Worker::from_fiber(ptr!(self), fiber) }
-		}
+	/// Create a worker with a stack of the size in `hint`
+	///
+	/// # Safety
+	/// Executor must outlive the worker
+	///
+	/// # Panics
+	/// If the stack allocation fails
+	#[allow(clippy::expect_used)]
+	pub unsafe fn new_worker_with_hint(&self, start: Start, hint: StackHint) -> Worker {
+		let fiber = match &self.pool {
+			Some(pool) => pool.new_fiber_with_hint(start, hint),
+			None => FiberBuilder::new()
+				.stack_hint(hint)
+				.build_with_start(start)
+				.expect("Failed to allocate stack for fiber")
+		};
+
+		/* Safety: guaranteed by caller */
+		unsafe { Worker::from_fiber(ptr!(self), fiber) }
 	}
 
 	/// Workers move themselves onto their own stack when
@@ -78,6 +156,7 @@ impl Executor {
 		unsafe { assert_unsafe_precondition!(!from.is_null(), "Double suspend detected") }
 
 		self.current.set(from);
+		self.block_runtime_signals();
 
 		#[cfg(debug_assertions)]
 		/* Safety: clear the caller */
@@ -135,12 +214,11 @@ impl Executor {
 
 		/* Safety: guaranteed by caller */
 		unsafe {
-			if self.pool.is_null() {
-				worker.into_inner().exit(ptr!(from=>fiber()));
-			} else {
-				worker
+			match &self.pool {
+				Some(pool) => worker
 					.into_inner()
-					.exit_to_pool(ptr!(from=>fiber()), self.pool);
+					.exit_to_pool(ptr!(from=>fiber()), ptr!(pool)),
+				None => worker.into_inner().exit(ptr!(from=>fiber()))
 			}
 		}
 	}
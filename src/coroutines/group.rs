@@ -0,0 +1,271 @@
+//! A dynamic set of tasks, joined in completion order
+//!
+//! ```
+//! let mut group = TaskGroup::new();
+//!
+//! for conn in connections {
+//! 	/* Safety: the connections are owned by the tasks, and the group is
+//! 	 * dropped by this task */
+//! 	unsafe { group.spawn(env, handle_connection(conn)) };
+//! }
+//!
+//! while let Some(result) = group.join_next().await? { /* ... */ }
+//! ```
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::*;
+use crate::async_std::AsyncIterator;
+use crate::cell::{Cell, UnsafeCell};
+
+struct GroupState<Output> {
+	ready: UnsafeCell<VecDeque<SpawnResult<Output>>>,
+	waiter: Cell<ReqPtr<()>>
+}
+
+#[asynchronous]
+impl<Output> GroupState<Output> {
+	fn push(&self, result: SpawnResult<Output>) {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.ready.as_mut() }.push_back(result);
+
+		let waiter = self.waiter.replace(Ptr::null());
+
+		if !waiter.is_null() {
+			/* Safety: the group is waiting for a result */
+			unsafe { Request::complete(waiter, ()) };
+		}
+	}
+
+	fn pop(&self) -> Option<SpawnResult<Output>> {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.ready.as_mut() }.pop_front()
+	}
+
+	fn ready(&self) -> usize {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.ready.as_ref() }.len()
+	}
+
+	#[future]
+	fn wait(&self, request: _) {
+		#[cancel]
+		fn cancel(&self) -> Result<()> {
+			let waiter = self.waiter.replace(Ptr::null());
+
+			if !waiter.is_null() {
+				/* Safety: we took ownership of waking up the task. it sees the
+				 * interrupt when it checks for one */
+				unsafe { Request::complete(waiter, ()) };
+			}
+
+			Ok(())
+		}
+
+		if self.ready() != 0 {
+			Progress::Done(())
+		} else {
+			self.waiter.set(request);
+
+			Progress::Pending(cancel(self))
+		}
+	}
+}
+
+/// Runs a task in a group, queueing its result once it completes
+struct GroupTask<T, Output> {
+	task: T,
+	state: Rc<GroupState<Output>>
+}
+
+#[asynchronous(task)]
+impl<T, Output> Task for GroupTask<T, Output>
+where
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	type Output = ();
+
+	async fn run(self) {
+		let Self { task, state } = self;
+		let context = get_context().await;
+
+		/* Safety: we are in an async function */
+		let result = catch_unwind_safe(|| unsafe { scoped(context, task) });
+
+		state.push(result);
+	}
+}
+
+/// A group of spawned tasks whose results are returned in the order that they
+/// complete
+///
+/// Dropping the group signals the remaining tasks to cancel and waits for
+/// them to complete, like [`TaskGroup::shutdown`]
+pub struct TaskGroup<Output> {
+	state: Rc<GroupState<Output>>,
+	handles: Vec<JoinHandle<()>>,
+	remaining: usize,
+
+	/* the context of the task that owns the group, which waits for the
+	 * remaining tasks on drop */
+	context: Ptr<Context>
+}
+
+#[asynchronous]
+impl<Output> TaskGroup<Output> {
+	/// Creates an empty task group
+	#[must_use]
+	pub fn new() -> Self {
+		let state = GroupState {
+			ready: UnsafeCell::new(VecDeque::new()),
+			waiter: Cell::new(Ptr::null())
+		};
+
+		Self {
+			state: Rc::new(state),
+			handles: Vec::new(),
+			remaining: 0,
+			context: Ptr::null()
+		}
+	}
+
+	/// The number of tasks that have yet to be joined
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.remaining
+	}
+
+	/// Returns true if every task in the group has been joined
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.remaining == 0
+	}
+
+	/// Spawn a new async task in the group
+	///
+	/// # Safety
+	/// The cloned `env` and `task` must outlive the spawned fiber. `env` must
+	/// be the environment of the current task, and the group must be dropped
+	/// by the current task
+	#[track_caller]
+	pub unsafe fn spawn<E, T>(&mut self, env: &E, task: T)
+	where
+		E: Environment,
+		T: for<'ctx> Task<Output<'ctx> = Output>
+	{
+		/* Safety: guaranteed by caller */
+		unsafe { self.spawn_with_hint(env, task, StackHint::Default) };
+	}
+
+	/// Same as [`TaskGroup::spawn`], with a stack of the size in `hint`
+	///
+	/// # Safety
+	/// See [`TaskGroup::spawn`]
+	#[track_caller]
+	pub unsafe fn spawn_with_hint<E, T>(&mut self, env: &E, task: T, hint: StackHint)
+	where
+		E: Environment,
+		T: for<'ctx> Task<Output<'ctx> = Output>
+	{
+		let task = GroupTask { task, state: self.state.clone() };
+
+		if self.handles.len() == self.handles.capacity() {
+			/* drop completed tasks before growing the list */
+			self.handles.retain(|handle| !handle.is_done());
+		}
+
+		/* Safety: guaranteed by caller */
+		let handle = unsafe { spawn_with_hint(env, task, hint) };
+
+		self.handles.push(handle);
+		self.context = ptr!(env.context());
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.remaining += 1);
+	}
+
+	/// Wait for the next task to complete, and return its result. Returns
+	/// `None` if the group is empty
+	///
+	/// # Cancel safety
+	///
+	/// This function is cancel safe. Once the interrupt is cleared, call this
+	/// function again to resume the operation.
+	pub async fn try_join_next(&mut self) -> Result<Option<SpawnResult<Output>>> {
+		loop {
+			if self.remaining == 0 {
+				return Ok(None);
+			}
+
+			if let Some(result) = self.state.pop() {
+				#[allow(clippy::arithmetic_side_effects)]
+				(self.remaining -= 1);
+
+				return Ok(Some(result));
+			}
+
+			check_interrupt().await?;
+
+			block_on(self.state.wait()).await;
+		}
+	}
+
+	/// Same as [`TaskGroup::try_join_next`]
+	///
+	/// # Panics
+	/// If the task panicked, the panic is resumed on the caller
+	pub async fn join_next(&mut self) -> Result<Option<Output>> {
+		Ok(self.try_join_next().await?.map(runtime::join))
+	}
+
+	/// Signals every task in the group to cancel, without waiting for them
+	pub fn cancel_all(&self) {
+		for handle in &self.handles {
+			if let Err(err) = handle.request_cancel() {
+				debug!(target: handle, ">> Cancel failed: {:?}", err);
+			}
+		}
+	}
+
+	/// Signals every task in the group to cancel, and waits for all of them
+	/// to complete. Results, including panics, are discarded
+	pub async fn shutdown(&mut self) {
+		self.cancel_all();
+
+		/* the tasks are already cancelling, and we must wait for them */
+		let _guard = interrupt_guard().await;
+
+		while let Ok(Some(_)) = self.try_join_next().await {}
+
+		self.handles.clear();
+	}
+}
+
+impl<Output> Default for TaskGroup<Output> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Output> Drop for TaskGroup<Output> {
+	fn drop(&mut self) {
+		if self.remaining == 0 {
+			return;
+		}
+
+		/* Safety: the group is dropped by the task that spawned into it, as
+		 * guaranteed by the contract of `spawn` */
+		unsafe { scoped(self.context.as_ref(), self.shutdown()) };
+	}
+}
+
+#[asynchronous]
+impl<Output> AsyncIterator for TaskGroup<Output> {
+	type Item = Result<Output>;
+
+	/// Returns the next result, or `None` if the group is empty
+	async fn next(&mut self) -> Option<Result<Output>> {
+		self.join_next().await.transpose()
+	}
+}
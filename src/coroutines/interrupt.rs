@@ -0,0 +1,120 @@
+//! Reasons for interrupting a task
+//!
+//! An interrupt may carry an [`InterruptReason`], given to
+//! [`Context::interrupt_with`] or [`JoinHandle::request_cancel_with`]. The
+//! interrupted task sees it in the [`Error`] returned from
+//! [`check_interrupt`] and the operations that fail when interrupted, and in
+//! the value returned from [`take_interrupt`]
+//!
+//! ```
+//! match read_request(&mut conn).await {
+//! 	Err(err) if err.is_interrupted() => {
+//! 		if let Some(reason) = err.downcast_ref::<InterruptReason>() {
+//! 			info!("Stopped reading: {}", reason);
+//! 		}
+//! 	} /* ... */
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+use super::*;
+use crate::error::internal::ErrorImpl;
+
+/// Why a task was interrupted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum InterruptKind {
+	/// The task was cancelled by its owner
+	#[default]
+	Cancelled,
+
+	/// The task took too long
+	Timeout,
+
+	/// The program or service is shutting down
+	Shutdown,
+
+	/// The other end of a connection asked for the task to stop
+	Peer
+}
+
+impl Display for InterruptKind {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		fmt.write_str(match self {
+			Self::Cancelled => "cancelled",
+			Self::Timeout => "timed out",
+			Self::Shutdown => "shutting down",
+			Self::Peer => "cancelled by peer"
+		})
+	}
+}
+
+/// The reason for an interrupt, and an optional message describing it
+///
+/// Converts into an [`Error`] of kind [`ErrorKind::Interrupted`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InterruptReason {
+	kind: InterruptKind,
+	message: Option<Cow<'static, str>>
+}
+
+impl InterruptReason {
+	#[must_use]
+	pub const fn new(kind: InterruptKind) -> Self {
+		Self { kind, message: None }
+	}
+
+	#[must_use]
+	pub fn with_message<M>(kind: InterruptKind, message: M) -> Self
+	where
+		M: Into<Cow<'static, str>>
+	{
+		Self { kind, message: Some(message.into()) }
+	}
+
+	#[must_use]
+	pub const fn kind(&self) -> InterruptKind {
+		self.kind
+	}
+
+	#[must_use]
+	pub fn message(&self) -> Option<&str> {
+		self.message.as_deref()
+	}
+}
+
+impl From<InterruptKind> for InterruptReason {
+	fn from(kind: InterruptKind) -> Self {
+		Self::new(kind)
+	}
+}
+
+impl Display for InterruptReason {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		write!(fmt, "Interrupted ({})", self.kind)?;
+
+		if let Some(message) = &self.message {
+			write!(fmt, ": {}", message)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl error::Error for InterruptReason {}
+
+impl ErrorImpl for InterruptReason {
+	fn kind(&self) -> ErrorKind {
+		ErrorKind::Interrupted
+	}
+}
+
+/// The error for an interrupt, carrying its reason if one was given
+pub(super) fn reason_error(reason: Option<&InterruptReason>) -> Error {
+	reason.map_or_else(
+		|| ErrorKind::Interrupted.into(),
+		|reason| reason.clone().into()
+	)
+}
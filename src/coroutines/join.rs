@@ -91,3 +91,111 @@ where
 	/* Safety: this is a join */
 	unsafe { Join::from_branch(branch.flatten()) }
 }
+
+/// Joins every future in `futures`, returning their outputs in the same
+/// order
+///
+/// If a future panics, an attempt to cancel the others is made, then the
+/// panic resumes on the caller
+#[asynchronous]
+pub async fn join_all_future<I, F>(futures: I) -> Vec<F::Output>
+where
+	I: IntoIterator<Item = F>,
+	F: Future
+{
+	/* Safety: should_cancel does not unwind */
+	let BranchAllOutput(_, results) = unsafe { branch_all(futures, result::Result::is_err).await };
+
+	results
+		.into_iter()
+		.map(|result| {
+			/* Safety: futures are only skipped after an earlier one panics, which
+			 * resumes first */
+			runtime::join(unsafe { result.expect_unchecked("Branch failed") })
+		})
+		.collect()
+}
+
+/// Joins every task in `tasks` and waits for all of them to finish,
+/// returning their outputs in the same order
+///
+/// If a task panics, an attempt to cancel the others is made, then the panic
+/// resumes on the caller
+///
+/// # Safety
+/// The cloned `env` and the tasks must outlive their spawned fiber
+#[asynchronous]
+pub async unsafe fn join_all<E, I, T, Output>(env: &E, tasks: I) -> Vec<Output>
+where
+	E: Environment,
+	I: IntoIterator<Item = T>,
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	let futures = tasks.into_iter().map(|task| {
+		/* Safety: guaranteed by caller */
+		unsafe { spawn_task_with_env(env, task) }
+	});
+
+	/* Safety: should_cancel does not unwind */
+	let BranchAllOutput(_, results) =
+		unsafe { branch_all(futures, |result| !matches!(result, Ok(Ok(_)))).await }.flatten();
+
+	results
+		.into_iter()
+		.map(|result| {
+			/* Safety: tasks are only skipped after an earlier one panics, which
+			 * resumes first */
+			runtime::join(unsafe { result.expect_unchecked("Branch failed") })
+		})
+		.collect()
+}
+
+/// Joins every task in `tasks`, returning their outputs in the same order
+///
+/// Once a task returns an error, the other tasks are cancelled and the
+/// error is returned. If a task panics, an attempt to cancel the others is
+/// made, then the panic resumes on the caller
+///
+/// # Safety
+/// The cloned `env` and the tasks must outlive their spawned fiber
+#[asynchronous]
+pub async unsafe fn try_join_all<E, I, T, Output, Error>(
+	env: &E, tasks: I
+) -> result::Result<Vec<Output>, Error>
+where
+	E: Environment,
+	I: IntoIterator<Item = T>,
+	T: for<'ctx> Task<Output<'ctx> = result::Result<Output, Error>>
+{
+	let futures = tasks.into_iter().map(|task| {
+		/* Safety: guaranteed by caller */
+		unsafe { spawn_task_with_env(env, task) }
+	});
+
+	/* Safety: should_cancel does not unwind */
+	let BranchAllOutput(first, results) =
+		unsafe { branch_all(futures, |result| !matches!(result, Ok(Ok(Ok(_))))).await }.flatten();
+
+	let mut outputs = Vec::with_capacity(results.len());
+	let mut error = None;
+
+	for (index, result) in results.into_iter().enumerate() {
+		/* tasks are only skipped once another one has failed */
+		let Some(result) = result else {
+			continue;
+		};
+
+		match runtime::join(result) {
+			Ok(output) => outputs.push(output),
+
+			/* cancelled tasks may fail as well. prefer the error that caused the cancel */
+			Err(err) if error.is_none() || first == Some(index) => error = Some(err),
+			Err(_) => ()
+		}
+	}
+
+	match error {
+		None => Ok(outputs),
+		Some(err) => Err(err)
+	}
+}
@@ -0,0 +1,308 @@
+//! Task-local storage
+//!
+//! Values are stored in the [`Context`] of the worker they were set on, in a
+//! slot reserved for each [`LocalKey`], so reading one is an index into the
+//! context's slots rather than a map lookup
+//!
+//! ```
+//! task_local! {
+//! 	static REQUEST_ID: u64;
+//! }
+//!
+//! REQUEST_ID
+//! 	.scope(id, async {
+//! 		REQUEST_ID.with(|id| info!("Handling request {}", id)).await;
+//! 	})
+//! 	.await;
+//! ```
+
+use std::marker::PhantomData;
+use std::mem::replace;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::*;
+
+use super::*;
+
+/// Copies a value into storage owned by a spawned task
+#[derive(Clone, Copy)]
+pub(super) struct Inherit {
+	clone: unsafe fn(Ptr<()>) -> Ptr<()>,
+	drop: unsafe fn(Ptr<()>)
+}
+
+impl Inherit {
+	/// # Safety
+	/// `value` must be a valid `T`
+	unsafe fn clone_value<T: Clone>(value: Ptr<()>) -> Ptr<()> {
+		/* Safety: guaranteed by caller */
+		let value = unsafe { value.cast::<T>().as_ref() };
+
+		MutPtr::from(Box::into_raw(Box::new(value.clone())))
+			.cast_const()
+			.cast()
+	}
+
+	/// # Safety
+	/// `value` must be returned from `clone_value::<T>`, and not used
+	/// afterwards
+	unsafe fn drop_value<T>(value: Ptr<()>) {
+		/* Safety: guaranteed by caller */
+		drop(unsafe { Box::from_raw(value.cast::<T>().cast_mut().as_mut_ptr()) });
+	}
+
+	const fn new<T: Clone>() -> Self {
+		Self {
+			clone: Self::clone_value::<T>,
+			drop: Self::drop_value::<T>
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+pub(super) struct Slot {
+	value: Ptr<()>,
+	inherit: Option<Inherit>
+}
+
+impl Slot {
+	const EMPTY: Self = Self { value: Ptr::null(), inherit: None };
+}
+
+/// The task-local values of a worker
+pub(super) struct Locals {
+	slots: Vec<Slot>,
+
+	/* values inherited from the parent, which we must drop */
+	owned: Vec<(Ptr<()>, Inherit)>
+}
+
+impl Locals {
+	pub(super) const fn new() -> Self {
+		Self { slots: Vec::new(), owned: Vec::new() }
+	}
+
+	pub(super) fn get(&self, index: usize) -> Ptr<()> {
+		self.slots.get(index).map_or(Ptr::null(), |slot| slot.value)
+	}
+
+	pub(super) fn replace(&mut self, index: usize, slot: Slot) -> Slot {
+		if index >= self.slots.len() {
+			#[allow(clippy::arithmetic_side_effects)]
+			self.slots.resize(index + 1, Slot::EMPTY);
+		}
+
+		/* Safety: we just resized the slots */
+		let current = unsafe { self.slots.get_unchecked_mut(index) };
+
+		replace(current, slot)
+	}
+
+	/// Copy the values of inheritable keys from `parent`
+	///
+	/// # Safety
+	/// The values in `parent` must be valid
+	pub(super) unsafe fn inherit(&mut self, parent: &Self) {
+		for (index, slot) in parent.slots.iter().enumerate() {
+			let Some(inherit) = slot.inherit else {
+				continue;
+			};
+
+			if slot.value.is_null() {
+				continue;
+			}
+
+			/* Safety: guaranteed by caller */
+			let value = unsafe { (inherit.clone)(slot.value) };
+
+			self.owned.push((value, inherit));
+			self.replace(index, Slot { value, inherit: Some(inherit) });
+		}
+	}
+}
+
+impl Drop for Locals {
+	fn drop(&mut self) {
+		for (value, inherit) in self.owned.drain(..) {
+			/* Safety: we own the value */
+			unsafe { (inherit.drop)(value) };
+		}
+	}
+}
+
+struct LocalGuard<'ctx> {
+	context: &'ctx Context,
+	index: usize,
+	prev: Slot
+}
+
+impl Drop for LocalGuard<'_> {
+	fn drop(&mut self) {
+		self.context.replace_local(self.index, self.prev);
+	}
+}
+
+/// A key for task-local storage, declared with [`task_local!`]
+///
+/// Each task sees its own value, set with [`LocalKey::scope`]. Keys declared
+/// as `#[inherit]` pass a clone of their value on to tasks spawned while it
+/// is set
+pub struct LocalKey<T: 'static> {
+	index: AtomicUsize,
+	inherit: Option<Inherit>,
+	phantom: PhantomData<fn() -> T>
+}
+
+const UNASSIGNED: usize = usize::MAX;
+
+#[asynchronous]
+impl<T: 'static> LocalKey<T> {
+	#[doc(hidden)]
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			index: AtomicUsize::new(UNASSIGNED),
+			inherit: None,
+			phantom: PhantomData
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub const fn new_inherited() -> Self
+	where
+		T: Clone
+	{
+		Self {
+			index: AtomicUsize::new(UNASSIGNED),
+			inherit: Some(Inherit::new::<T>()),
+			phantom: PhantomData
+		}
+	}
+
+	fn index(&self) -> usize {
+		static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+		let index = self.index.load(Relaxed);
+
+		if index != UNASSIGNED {
+			return index;
+		}
+
+		let next = NEXT.fetch_add(1, Relaxed);
+
+		match self
+			.index
+			.compare_exchange(UNASSIGNED, next, Relaxed, Relaxed)
+		{
+			Ok(_) => next,
+			Err(index) => index
+		}
+	}
+
+	/// Set the value of this key to `value` while running `task`
+	///
+	/// The previous value, if any, is restored afterwards
+	pub async fn scope<X, Output>(&'static self, value: T, task: X) -> Output
+	where
+		X: for<'ctx> Task<Output<'ctx> = Output>
+	{
+		let context = get_context().await;
+		let index = self.index();
+		let slot = Slot { value: ptr!(&value).cast(), inherit: self.inherit };
+		let prev = context.replace_local(index, slot);
+		let _guard = LocalGuard { context, index, prev };
+
+		/* Safety: we are in an async function */
+		unsafe { scoped(context, task) }
+	}
+
+	/// Calls `func` with a reference to the value of this key, or returns
+	/// `None` if it isn't set
+	pub async fn try_with<F, Output>(&'static self, func: F) -> Option<Output>
+	where
+		F: FnOnce(&T) -> Output
+	{
+		let value = get_context().await.local(self.index());
+
+		if value.is_null() {
+			return None;
+		}
+
+		/* Safety: the value is alive until its scope ends, and the scope can't
+		 * end while we are running */
+		Some(func(unsafe { value.cast::<T>().as_ref() }))
+	}
+
+	/// Calls `func` with a reference to the value of this key
+	///
+	/// # Panics
+	/// If the value isn't set
+	pub async fn with<F, Output>(&'static self, func: F) -> Output
+	where
+		F: FnOnce(&T) -> Output
+	{
+		#[allow(clippy::expect_used)]
+		self.try_with(func)
+			.await
+			.expect("Task-local value is not set")
+	}
+
+	/// Returns a copy of the value of this key, or `None` if it isn't set
+	pub async fn get(&'static self) -> Option<T>
+	where
+		T: Clone
+	{
+		self.try_with(T::clone).await
+	}
+}
+
+impl<T: 'static> Default for LocalKey<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Declare keys for task-local storage
+///
+/// Keys marked `#[inherit]` must have a [`Clone`] type. Tasks spawned while
+/// their value is set start with a clone of it
+///
+/// ```
+/// task_local! {
+/// 	static REQUEST_ID: u64;
+///
+/// 	#[inherit]
+/// 	pub static TRACE: Rc<Span>;
+/// }
+/// ```
+#[macro_export]
+macro_rules! task_local {
+	() => {};
+
+	(
+		#[inherit]
+		$(#[$attr:meta])*
+		$vis:vis static $name:ident: $type:ty;
+		$($rest:tt)*
+	) => {
+		$(#[$attr])*
+		$vis static $name: $crate::coroutines::LocalKey<$type> =
+			$crate::coroutines::LocalKey::new_inherited();
+
+		$crate::coroutines::task_local!($($rest)*);
+	};
+
+	(
+		$(#[$attr:meta])*
+		$vis:vis static $name:ident: $type:ty;
+		$($rest:tt)*
+	) => {
+		$(#[$attr])*
+		$vis static $name: $crate::coroutines::LocalKey<$type> =
+			$crate::coroutines::LocalKey::new();
+
+		$crate::coroutines::task_local!($($rest)*);
+	};
+}
+
+pub use task_local;
@@ -12,12 +12,18 @@ use crate::{debug, trace, warn};
 mod lang {}
 
 pub mod branch;
+pub mod cancel;
 pub mod context;
 pub mod environment;
 pub mod executor;
+pub mod group;
 pub mod impls;
+pub mod interrupt;
 pub mod join;
+pub mod local;
 pub mod ops;
+pub mod registry;
+pub mod scope;
 pub mod select;
 pub mod spawn;
 pub mod wake;
@@ -27,7 +33,8 @@ pub mod internal;
 
 #[doc(inline)]
 pub use {
-	context::*, environment::*, executor::*, join::*, select::*, spawn::*, wake::*, worker::*
+	cancel::*, context::*, environment::*, executor::*, group::*, interrupt::*, join::*, local::*,
+	registry::*, scope::*, select::*, spawn::*, wake::*, worker::*
 };
 
 use self::branch::*;
@@ -162,7 +169,8 @@ pub async fn current_budget() -> u32 {
 /// Returns whether or not the budget was successfully acquired.
 ///
 /// Note that this function does not do any suspending itself. It is up to the
-/// caller to suspend if this function returns `false`.
+/// caller to suspend if this function returns `false`, see [`consume_budget`]
+/// for a version that does.
 #[asynchronous]
 #[allow(clippy::impl_trait_in_params)]
 pub async fn acquire_budget(amount: impl Into<Option<u32>>) -> bool {
@@ -171,6 +179,68 @@ pub async fn acquire_budget(amount: impl Into<Option<u32>>) -> bool {
 	get_context().await.decrease_budget(amount).is_some()
 }
 
+/// Hands `request` to `waker`, which completes it from the event loop
+///
+/// # Safety
+/// `waker` must belong to the current thread, and be alive until the request
+/// completes
+#[future]
+unsafe fn reschedule(waker: &Waker, request: _) {
+	#[cancel]
+	fn cancel() -> Result<()> {
+		/* the request is already queued, and completes shortly */
+		Ok(())
+	}
+
+	/* Safety: guaranteed by caller */
+	unsafe {
+		waker.prepare();
+		waker.wake(request);
+	}
+
+	Progress::Pending(cancel())
+}
+
+/// Suspend the current worker, and resume it from the runtime's event loop
+///
+/// This gives other workers a chance to run during a long computation that
+/// never suspends on its own. Yielding also refills the worker's budget
+///
+/// The worker is resumed through the runtime's [`Waker`]. With an
+/// [`EventFdWaker`], workers that yield are resumed in the order that they
+/// yielded, but there is no ordering relative to other events, such as
+/// completed I/O
+///
+/// If the current async runtime cannot reschedule workers, the budget is
+/// refilled and this returns immediately
+#[asynchronous]
+pub async fn yield_now() {
+	let context = get_context().await;
+	let Some(waker) = context.waker() else {
+		context.reset_budget();
+
+		return;
+	};
+
+	/* Safety: the waker belongs to this thread, and is owned by the context
+	 * which stays alive while we are blocked
+	 */
+	block_on(unsafe { reschedule(waker) }).await;
+}
+
+/// Consume one unit of the current worker's budget, yielding with
+/// [`yield_now`] if it runs out
+///
+/// Operations that can complete without suspending, such as receiving from a
+/// channel that has values ready, call this function so that a busy worker
+/// can't starve the others
+#[asynchronous]
+pub async fn consume_budget() {
+	if !matches!(get_context().await.decrease_budget(1), Some(1..)) {
+		yield_now().await;
+	}
+}
+
 /// An async worker that is being cancelled is in an interrupted state.
 ///
 /// Most I/O and blocking operations like timers, locking a mutex, or receiving
@@ -184,16 +254,29 @@ pub async fn is_interrupted() -> bool {
 
 /// See [`is_interrupted`]
 ///
-/// If the current worker is interrupted, returns an interrupted error
+/// If the current worker is interrupted, returns an interrupted error. The
+/// error carries the [`InterruptReason`], if one was given
 #[asynchronous]
 pub async fn check_interrupt() -> Result<()> {
-	if !is_interrupted().await {
+	let context = get_context().await;
+
+	if !context.interrupted() {
 		Ok(())
 	} else {
-		Err(ErrorKind::Interrupted.into())
+		Err(context.interrupt_error())
 	}
 }
 
+/// The error for an operation that failed because the current worker is
+/// interrupted, carrying the [`InterruptReason`] if one was given
+///
+/// Use this to report interrupts detected by other means, such as a
+/// cancelled wait
+#[asynchronous]
+pub async fn interrupt_error() -> Error {
+	get_context().await.interrupt_error()
+}
+
 /// Removes an active interrupt on this worker, if any
 ///
 /// I/O and blocking operations will work again after this call, but if the
@@ -208,20 +291,15 @@ pub async fn clear_interrupt() {
 	get_context().await.clear_interrupt();
 }
 
-/// Returns whether or not the current worker is interrupted.
+/// Returns the reason the current worker is interrupted, or `None` if it
+/// isn't. Interrupts without a reason return the default reason
 ///
-/// If so, the interrupt is cleared.
+/// If interrupted, the interrupt is cleared.
 ///
 /// See [`clear_interrupt`]
 #[asynchronous]
-pub async fn take_interrupt() -> bool {
-	let interrupted = is_interrupted().await;
-
-	if interrupted {
-		clear_interrupt().await;
-	}
-
-	interrupted
+pub async fn take_interrupt() -> Option<InterruptReason> {
+	get_context().await.take_interrupt()
 }
 
 /// Returns an error if the current worker is interrupted.
@@ -231,11 +309,17 @@ pub async fn take_interrupt() -> bool {
 /// See [`check_interrupt`] and [`take_interrupt`]
 #[asynchronous]
 pub async fn check_interrupt_take() -> Result<()> {
-	if !take_interrupt().await {
-		Ok(())
-	} else {
-		Err(ErrorKind::Interrupted.into())
+	let context = get_context().await;
+
+	if !context.interrupted() {
+		return Ok(());
 	}
+
+	let error = context.interrupt_error();
+
+	context.clear_interrupt();
+
+	Err(error)
 }
 
 /// Creates an interrupt guard
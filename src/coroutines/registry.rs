@@ -0,0 +1,225 @@
+//! Task identity and the registry of live tasks
+//!
+//! Every [`Context`] is given a [`TaskId`] when it's created. Spawned tasks
+//! also record their name and where they were spawned, and are registered
+//! with their executor while running, so that a dump of everything running
+//! on an executor can be printed when a service hangs
+//!
+//! ```
+//! let mut dump = signal(Signal::User1)?;
+//!
+//! while dump.next().await.is_some() {
+//! 	eprintln!("{}", dump_tasks().await);
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter, Write};
+use std::panic::Location;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::*;
+
+use super::*;
+use crate::cell::{Cell, UnsafeCell};
+
+/// A unique identifier for a task, increasing in the order that tasks are
+/// created
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+impl TaskId {
+	fn next() -> Self {
+		static NEXT: AtomicU64 = AtomicU64::new(1);
+
+		Self(NEXT.fetch_add(1, Relaxed))
+	}
+
+	#[must_use]
+	pub const fn as_u64(self) -> u64 {
+		self.0
+	}
+}
+
+impl Display for TaskId {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		write!(fmt, "#{}", self.0)
+	}
+}
+
+/// What a task is currently doing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+	/// The task is running, or waiting on a task it resumed
+	Running,
+
+	/// The task was suspended manually, see [`Environment::suspend`]
+	Suspended,
+
+	/// The task is blocked on a future, named by its type
+	Blocked(&'static str)
+}
+
+impl Display for TaskState {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Running => fmt.write_str("running"),
+			Self::Suspended => fmt.write_str("suspended"),
+			Self::Blocked(future) => write!(fmt, "blocked on {}", future)
+		}
+	}
+}
+
+/// The identity and state of a task
+pub struct TaskInfo {
+	id: TaskId,
+	name: Option<Cow<'static, str>>,
+	location: Option<&'static Location<'static>>,
+	state: Cell<TaskState>,
+
+	/* the position in the executor's registry */
+	index: Cell<usize>
+}
+
+impl TaskInfo {
+	pub(super) fn new() -> Self {
+		Self {
+			id: TaskId::next(),
+			name: None,
+			location: None,
+			state: Cell::new(TaskState::Running),
+			index: Cell::new(usize::MAX)
+		}
+	}
+
+	pub(super) fn set_spawn(
+		&mut self, name: Option<Cow<'static, str>>, location: &'static Location<'static>
+	) {
+		self.name = name;
+		self.location = Some(location);
+	}
+
+	pub(super) fn set_state(&self, state: TaskState) {
+		self.state.set(state);
+	}
+
+	#[must_use]
+	pub const fn id(&self) -> TaskId {
+		self.id
+	}
+
+	/// The name given to [`spawn_named`], if any
+	#[must_use]
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// Where the task was spawned, if it was spawned
+	#[must_use]
+	pub const fn location(&self) -> Option<&'static Location<'static>> {
+		self.location
+	}
+
+	#[must_use]
+	pub fn state(&self) -> TaskState {
+		self.state.get()
+	}
+}
+
+impl Display for TaskInfo {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+		write!(fmt, "{} {}", self.id, self.name().unwrap_or("<unnamed>"))?;
+
+		if let Some(location) = self.location {
+			write!(fmt, " spawned at {}", location)?;
+		}
+
+		write!(fmt, ": {}", self.state())
+	}
+}
+
+/// The tasks that are running on an executor
+pub(super) struct Registry {
+	tasks: UnsafeCell<Vec<Ptr<TaskInfo>>>
+}
+
+impl Registry {
+	pub(super) const fn new() -> Self {
+		Self { tasks: UnsafeCell::new(Vec::new()) }
+	}
+
+	/// # Safety
+	/// `task` must be alive until it's removed, and must not already be in a
+	/// registry
+	pub(super) unsafe fn insert(&self, task: Ptr<TaskInfo>) {
+		/* Safety: exclusive unsafe cell access */
+		let tasks = unsafe { self.tasks.as_mut() };
+
+		/* Safety: guaranteed by caller */
+		unsafe { ptr!(task=>index.set(tasks.len())) };
+
+		tasks.push(task);
+	}
+
+	/// # Safety
+	/// `task` must be in this registry
+	pub(super) unsafe fn remove(&self, task: Ptr<TaskInfo>) {
+		/* Safety: exclusive unsafe cell access */
+		let tasks = unsafe { self.tasks.as_mut() };
+
+		/* Safety: guaranteed by caller */
+		let index = unsafe { ptr!(task=>index.replace(usize::MAX)) };
+
+		tasks.swap_remove(index);
+
+		if let Some(moved) = tasks.get(index) {
+			/* Safety: tasks in the registry are alive */
+			unsafe { ptr!(moved=>index.set(index)) };
+		}
+	}
+
+	pub(super) fn len(&self) -> usize {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.tasks.as_ref() }.len()
+	}
+
+	pub(super) fn for_each<F>(&self, mut func: F)
+	where
+		F: FnMut(&TaskInfo)
+	{
+		/* tasks may not be spawned or exit while we're iterating, as we
+		 * never suspend
+		 *
+		 * Safety: exclusive unsafe cell access
+		 */
+		for task in unsafe { self.tasks.as_ref() } {
+			/* Safety: tasks in the registry are alive */
+			func(unsafe { task.as_ref() });
+		}
+	}
+
+	pub(super) fn dump(&self) -> String {
+		let mut dump = String::new();
+		let _ = writeln!(dump, "{} live tasks", self.len());
+
+		self.for_each(|task| {
+			let _ = writeln!(dump, "  {}", task);
+		});
+
+		dump
+	}
+}
+
+/// Get the identity and state of the current task
+#[asynchronous]
+pub async fn current_task<#[cx] 'current>() -> &'current TaskInfo {
+	get_context().await.task()
+}
+
+/// List every task running on the current executor, one per line
+#[asynchronous]
+pub async fn dump_tasks() -> String {
+	let executor = get_context().await.executor();
+
+	/* Safety: the executor outlives its tasks */
+	unsafe { executor.as_ref() }.dump_tasks()
+}
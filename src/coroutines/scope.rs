@@ -0,0 +1,276 @@
+//! Structured concurrency
+//!
+//! Tasks spawned in a [`Scope`] may borrow from the caller's stack, because
+//! [`scope`] does not return until every one of them has completed. If the
+//! body panics or the caller is interrupted, the remaining tasks are
+//! interrupted and then joined
+//!
+//! ```
+//! let mut counts = [0; 4];
+//! let counts_ref = &mut counts;
+//!
+//! scope(env, |scope| async move {
+//! 	for count in counts_ref {
+//! 		scope.spawn(count_items(count));
+//! 	}
+//! })
+//! .await;
+//! ```
+
+use std::marker::PhantomData;
+use std::mem::take;
+use std::rc::Rc;
+
+use super::*;
+use crate::cell::{Cell, UnsafeCell};
+use crate::impls::OptionExt;
+
+/// A task spawned in a scope
+pub(super) trait ScopeChild {
+	/// Returns true if the task is running and hasn't been cancelled
+	fn is_running(&self) -> bool;
+
+	/// Signals the task to cancel, if it's still running
+	fn request_cancel(&self) -> Result<()>;
+}
+
+/// Counts the tasks in a scope that have yet to complete
+pub(super) struct ScopeCounter {
+	pending: Cell<usize>,
+	waiter: Cell<ReqPtr<()>>
+}
+
+impl ScopeCounter {
+	const fn new() -> Self {
+		Self {
+			pending: Cell::new(0),
+			waiter: Cell::new(Ptr::null())
+		}
+	}
+
+	fn add(&self) {
+		self.pending.update(|pending| {
+			pending
+				.checked_add(1)
+				.expect_nounwind("Scope task count overflowed")
+		});
+	}
+
+	/// Marks one of the tasks as complete, waking the scope if it was the
+	/// last
+	///
+	/// # Safety
+	/// Must be called exactly once for every task that was added, after the
+	/// task completes. `this` may be dangling after this call
+	pub(super) unsafe fn complete(this: Ptr<Self>) {
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this.as_ref() };
+		let pending = this.pending.update(|pending| {
+			pending
+				.checked_sub(1)
+				.expect_nounwind("Scope task count underflowed")
+		});
+
+		if pending != 0 {
+			return;
+		}
+
+		let waiter = this.waiter.replace(Ptr::null());
+
+		if !waiter.is_null() {
+			/* Safety: the scope is waiting for its tasks to complete */
+			unsafe { Request::complete(waiter, ()) };
+		}
+	}
+}
+
+struct ScopeState<'env> {
+	counter: ScopeCounter,
+	children: UnsafeCell<Vec<Rc<dyn ScopeChild + 'env>>>,
+	cancelled: Cell<bool>,
+	closed: Cell<bool>
+}
+
+#[asynchronous]
+impl<'env> ScopeState<'env> {
+	fn push(&self, child: Rc<dyn ScopeChild + 'env>) {
+		/* Safety: exclusive unsafe cell access */
+		let children = unsafe { self.children.as_mut() };
+
+		if children.len() == children.capacity() {
+			/* drop completed tasks before growing the list */
+			children.retain(|child| child.is_running());
+		}
+
+		children.push(child);
+	}
+
+	fn cancel_all(&self) {
+		self.cancelled.set(true);
+
+		/* cancelling a task may spawn another, which is cancelled
+		 * immediately because of the flag above
+		 *
+		 * Safety: exclusive unsafe cell access
+		 */
+		let children = take(unsafe { self.children.as_mut() });
+
+		for child in &children {
+			if let Err(err) = child.request_cancel() {
+				debug!(target: self, ">> Cancel failed: {:?}", err);
+			}
+		}
+	}
+
+	#[future]
+	fn wait(&self, request: _) {
+		#[cancel]
+		fn cancel(&self) -> Result<()> {
+			self.cancel_all();
+
+			/* the scope still waits for the tasks to finish */
+			Ok(())
+		}
+
+		if self.counter.pending == 0 {
+			Progress::Done(())
+		} else {
+			self.counter.waiter.set(request);
+
+			Progress::Pending(cancel(self))
+		}
+	}
+}
+
+/// A scope to spawn tasks that may borrow data from outside of it
+///
+/// See [`scope`]
+pub struct Scope<'env, E> {
+	env: &'env E,
+	state: Rc<ScopeState<'env>>,
+
+	/* invariant, so that tasks can't borrow data that only lives inside the
+	 * scope */
+	phantom: PhantomData<&'env mut &'env ()>
+}
+
+impl<'env, E: Environment> Scope<'env, E> {
+	fn new(env: &'env E) -> Self {
+		let state = ScopeState {
+			counter: ScopeCounter::new(),
+			children: UnsafeCell::new(Vec::new()),
+			cancelled: Cell::new(false),
+			closed: Cell::new(false)
+		};
+
+		Self { env, state: Rc::new(state), phantom: PhantomData }
+	}
+
+	/// The environment tasks are spawned with
+	#[must_use]
+	pub const fn env(&self) -> &'env E {
+		self.env
+	}
+
+	/// Returns true if the tasks in this scope are being cancelled
+	#[must_use]
+	pub fn is_cancelled(&self) -> bool {
+		self.state.cancelled.get()
+	}
+
+	/// Spawn a new async task in this scope
+	///
+	/// The task may borrow anything that outlives the scope. The returned
+	/// [`JoinHandle`] may be used to get the task's result, and if it is
+	/// dropped instead, the scope still waits for the task to complete
+	///
+	/// # Panics
+	/// If the scope has already ended
+	#[track_caller]
+	pub fn spawn<T, Output>(&self, task: T) -> JoinHandle<Output>
+	where
+		T: for<'ctx> Task<Output<'ctx> = Output> + 'env,
+		Output: 'env
+	{
+		self.spawn_with_hint(task, StackHint::Default)
+	}
+
+	/// Same as [`Scope::spawn`], with a stack of the size in `hint`
+	///
+	/// # Panics
+	/// If the scope has already ended
+	#[track_caller]
+	pub fn spawn_with_hint<T, Output>(&self, task: T, hint: StackHint) -> JoinHandle<Output>
+	where
+		T: for<'ctx> Task<Output<'ctx> = Output> + 'env,
+		Output: 'env
+	{
+		assert!(!self.state.closed.get(), "Scope has already ended");
+
+		/* Safety: the env and task outlive the scope, which doesn't end until the
+		 * task completes */
+		let handle = unsafe { spawn_with_hint(self.env, task, hint) };
+
+		/* Safety: the counter is alive until every attached task completes */
+		if let Some(child) = unsafe { handle.attach_scope(ptr!(&self.state.counter)) } {
+			self.state.counter.add();
+
+			if self.state.cancelled.get() {
+				let _ = child.request_cancel();
+			}
+
+			self.state.push(child);
+		}
+
+		handle
+	}
+
+	/// Signals every task in this scope to cancel, including ones spawned
+	/// after this call, without waiting for them
+	pub fn cancel_all(&self) {
+		self.state.cancel_all();
+	}
+}
+
+impl<E> Clone for Scope<'_, E> {
+	fn clone(&self) -> Self {
+		Self {
+			env: self.env,
+			state: self.state.clone(),
+			phantom: PhantomData
+		}
+	}
+}
+
+/// Create a scope for spawning tasks that borrow from the current stack
+///
+/// `body` is called with a [`Scope`], and the task it returns is run on the
+/// current worker. Once it completes, `scope` waits for every task spawned in
+/// the scope before returning the body's output
+///
+/// If the body panics, or the current worker is interrupted, the tasks in the
+/// scope are interrupted. They are still joined before this function returns
+/// or resumes the panic
+#[asynchronous]
+pub async fn scope<'env, E, F, T, Output>(env: &'env E, body: F) -> Output
+where
+	E: Environment,
+	F: FnOnce(Scope<'env, E>) -> T,
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	let scope = Scope::new(env);
+	let context = get_context().await;
+
+	/* Safety: we are in an async function */
+	let result = catch_unwind_safe(|| unsafe { scoped(context, body(scope.clone())) });
+
+	if result.is_err() || is_interrupted().await {
+		scope.cancel_all();
+	}
+
+	block_on(scope.state.wait()).await;
+
+	scope.state.closed.set(true);
+
+	runtime::join(result)
+}
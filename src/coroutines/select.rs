@@ -3,6 +3,7 @@
 use std::result;
 
 use super::*;
+use crate::impls::OptionExt;
 
 /// The result of a call to [`fn@select`] or [`select_future`]
 #[derive(Debug)]
@@ -106,3 +107,81 @@ where
 
 	runtime::join(result.flatten())
 }
+
+/// Races every future in `futures`, waiting for one of them to finish and
+/// cancelling the rest
+///
+/// Returns the index and output of the future that completed first
+///
+/// # Panics
+/// If `futures` is empty
+#[asynchronous]
+pub async fn select_all_future<I, F>(futures: I) -> (usize, F::Output)
+where
+	I: IntoIterator<Item = F>,
+	F: Future
+{
+	/* Safety: should_cancel does not unwind */
+	let BranchAllOutput(first, mut results) = unsafe { branch_all(futures, |_| true).await };
+
+	#[allow(clippy::expect_used)]
+	let index = first.expect("`select_all` called with no futures");
+
+	/* Safety: the first future to complete has a result */
+	let result = unsafe {
+		results
+			.get_unchecked_mut(index)
+			.take()
+			.expect_unchecked("Branch failed")
+	};
+
+	(index, runtime::join(result))
+}
+
+/// Races every task in `tasks`, waiting for one of them to finish and
+/// cancelling the rest
+///
+/// Returns the index and output of the task that completed first
+///
+/// If any of the tasks panic, the panic is resumed on the caller
+///
+/// # Panics
+/// If `tasks` is empty
+///
+/// # Safety
+/// The cloned `env` and the tasks must outlive their spawned fiber
+#[asynchronous]
+pub async unsafe fn select_all<E, I, T, Output>(env: &E, tasks: I) -> (usize, Output)
+where
+	E: Environment,
+	I: IntoIterator<Item = T>,
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	let futures = tasks.into_iter().map(|task| {
+		/* Safety: guaranteed by caller */
+		unsafe { spawn_task_with_env(env, task) }
+	});
+
+	/* Safety: should_cancel does not unwind */
+	let BranchAllOutput(first, results) = unsafe { branch_all(futures, |_| true).await }.flatten();
+
+	#[allow(clippy::expect_used)]
+	let index = first.expect("`select_all` called with no tasks");
+	let mut output = None;
+
+	for (position, result) in results.into_iter().enumerate() {
+		let Some(result) = result else {
+			continue;
+		};
+
+		/* resume panics from the cancelled tasks as well */
+		let result = runtime::join(result);
+
+		if position == index {
+			output = Some(result);
+		}
+	}
+
+	/* Safety: the first task to complete has a result */
+	(index, unsafe { output.expect_unchecked("Branch failed") })
+}
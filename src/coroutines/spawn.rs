@@ -1,8 +1,11 @@
 #![allow(clippy::multiple_unsafe_ops_per_block)]
 
+use std::borrow::Cow;
 use std::mem::replace;
+use std::panic::Location;
 use std::rc::Rc;
 
+use super::scope::{ScopeChild, ScopeCounter};
 use super::*;
 use crate::cell::UnsafeCell;
 
@@ -66,6 +69,18 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 		/* Safety: worker is valid for this context */
 		unsafe { context.set_worker(worker) };
 
+		let executor = context.executor();
+		let task_info = ptr!(context.task());
+
+		/* Safety: worker is valid for this context */
+		let fiber = unsafe { ptr!(worker=>fiber()) };
+
+		/* Safety: the context outlives the task, and the fiber is running it */
+		unsafe {
+			ptr!(executor=>register(task_info));
+			ptr!(fiber=>set_owner(context.task().id().as_u64()));
+		}
+
 		let mut is_async = false;
 		let data = SpawnData::Pending(ptr!(!null & *context), ptr!(!null &mut is_async));
 
@@ -83,6 +98,12 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 		/* Safety: this is an async function */
 		let result = catch_unwind_safe(|| unsafe { context.run(task) });
 
+		/* Safety: registered above */
+		unsafe {
+			ptr!(executor=>unregister(task_info));
+			ptr!(fiber=>set_owner(0));
+		}
+
 		if is_async {
 			/* Safety: only called once
 			 * Note: Request::complete does not unwind
@@ -99,7 +120,7 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 	/// # Safety
 	/// The `env` and `task` must outlive the spawned fiber
 	#[future]
-	unsafe fn spawn(env: E, task: T, request: _) -> SpawnResult<Output> {
+	unsafe fn spawn(env: E, task: T, hint: StackHint, request: _) -> SpawnResult<Output> {
 		#[cancel]
 		fn cancel(context: NonNull<Context>) -> Result<()> {
 			/* Safety: guaranteed by Future's contract */
@@ -113,7 +134,7 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 		let executor = call_no_unwind(|| env.executor());
 
 		/* Safety: guaranteed by caller */
-		let worker = unsafe { ptr!(executor=>new_worker(start)) };
+		let worker = unsafe { ptr!(executor=>new_worker_with_hint(start, hint)) };
 
 		spawn.data = SpawnData::Start(env, task, worker, request);
 
@@ -143,6 +164,29 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 	}
 }
 
+/// Clone `env` for a new worker, which inherits the task-local values of the
+/// current one, and records its name and the caller's location
+///
+/// # Safety
+/// See [`Environment::clone`]
+#[track_caller]
+unsafe fn clone_env<E>(env: &E, name: Option<Cow<'static, str>>) -> E
+where
+	E: Environment
+{
+	/* Safety: guaranteed by caller */
+	let mut child = unsafe { env.clone() };
+	let parent = call_no_unwind(|| env.context());
+	let context = call_no_unwind(|| child.context_mut());
+
+	context.task_mut().set_spawn(name, Location::caller());
+
+	/* Safety: the parent is alive while it's spawning */
+	unsafe { context.inherit_locals(parent) };
+
+	child
+}
+
 /// Spawn a new worker. The result of the worker will be returned as a
 /// [`Future`]
 ///
@@ -152,6 +196,27 @@ impl<E: Environment, T: for<'ctx> Task<Output<'ctx> = Output>, Output> SpawnWork
 /// [`Future`]: crate::future::Future
 #[future]
 pub unsafe fn spawn_task<E, T, Output>(env: E, task: T, request: _) -> SpawnResult<Output>
+where
+	E: Environment,
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	#[cancel]
+	fn cancel(context: NonNull<Context>) -> Result<()> {
+		Ok(())
+	}
+
+	/* Safety: guaranteed by caller */
+	unsafe { spawn_task_with_hint(env, task, StackHint::Default).run(request) }
+}
+
+/// Same as [`spawn_task`], with a stack of the size in `hint`
+///
+/// # Safety
+/// The `env` and `task` must outlive the spawned fiber
+#[future]
+pub unsafe fn spawn_task_with_hint<E, T, Output>(
+	env: E, task: T, hint: StackHint, request: _
+) -> SpawnResult<Output>
 where
 	E: Environment,
 	T: for<'ctx> Task<Output<'ctx> = Output>
@@ -167,7 +232,7 @@ where
 
 	#[cfg(not(any(doc, feature = "xx-doc")))]
 	/* Safety: guaranteed by caller */
-	(unsafe { SpawnWorker::spawn(env, task).run(request) })
+	(unsafe { SpawnWorker::spawn(env, task, hint).run(request) })
 }
 
 /// Utility function that calls the above with the
@@ -187,30 +252,41 @@ where
 	}
 
 	/* Safety: guaranteed by caller */
-	unsafe { spawn_task(env.clone(), task).run(request) }
+	unsafe { spawn_task(clone_env(env, None), task).run(request) }
 }
 
 struct SpawnHandle<Output> {
 	#[allow(clippy::type_complexity)]
 	cancel: Option<CancelClosure<(NonNull<Context>, ReqPtr<SpawnResult<Output>>)>>,
 	output: Option<SpawnResult<Output>>,
-	waiter: ReqPtr<SpawnResult<Output>>
+	waiter: ReqPtr<SpawnResult<Output>>,
+	scope: Ptr<ScopeCounter>
+}
+
+struct Spawn<Output> {
+	request: Request<SpawnResult<Output>>,
+	handle: UnsafeCell<SpawnHandle<Output>>,
+
+	/* the reason for cancelling the task, taken by its context when it is
+	 * interrupted */
+	reason: UnsafeCell<Option<InterruptReason>>
 }
 
-impl<Output> SpawnHandle<Output> {
+impl<Output> Spawn<Output> {
 	/// # Safety
 	/// the future must be running
-	unsafe fn try_cancel(&mut self) -> Option<Result<()>> {
+	unsafe fn try_cancel(&self, reason: Option<InterruptReason>) -> Option<Result<()>> {
+		/* Safety: exclusive unsafe cell access */
+		let cancel = unsafe { self.handle.as_mut() }.cancel.take()?;
+
+		/* Safety: exclusive unsafe cell access */
+		*unsafe { self.reason.as_mut() } = reason;
+
 		/* Safety: guaranteed by caller */
-		self.cancel.take().map(|cancel| unsafe { cancel.run() })
+		Some(unsafe { cancel.run() })
 	}
 }
 
-struct Spawn<Output> {
-	request: Request<SpawnResult<Output>>,
-	handle: UnsafeCell<SpawnHandle<Output>>
-}
-
 #[cfg(not(any(doc, feature = "xx-doc")))]
 impl<Output> Spawn<Output> {
 	/// # Safety
@@ -223,6 +299,10 @@ impl<Output> Spawn<Output> {
 
 		/* Safety: exclusive unsafe cell access */
 		let handle = unsafe { this.handle.as_mut() };
+		let scope = handle.scope;
+
+		/* the task can no longer be cancelled */
+		handle.cancel = None;
 
 		if handle.waiter.is_null() {
 			handle.output = Some(output);
@@ -230,6 +310,17 @@ impl<Output> Spawn<Output> {
 			/* Safety: complete the future */
 			unsafe { Request::complete(handle.waiter, output) };
 		}
+
+		/* the output may borrow from the scope, so it must not be dropped after
+		 * the scope ends */
+		drop(this);
+
+		if !scope.is_null() {
+			/* Safety: the task has completed
+			 * Note: the scope may no longer be valid after this call
+			 */
+			unsafe { ScopeCounter::complete(scope) };
+		}
 	}
 
 	fn new() -> Self {
@@ -241,15 +332,20 @@ impl<Output> Spawn<Output> {
 				handle: UnsafeCell::new(SpawnHandle {
 					cancel: None,
 					output: None,
-					waiter: Ptr::null()
-				})
+					waiter: Ptr::null(),
+					scope: Ptr::null()
+				}),
+				reason: UnsafeCell::new(None)
 			}
 		}
 	}
 
 	/// # Safety
 	/// The cloned `env` and `task` must outlive the spawned fiber
-	unsafe fn run<E, T>(env: &E, task: T) -> JoinHandle<Output>
+	#[track_caller]
+	unsafe fn run<E, T>(
+		env: &E, task: T, hint: StackHint, name: Option<Cow<'static, str>>
+	) -> JoinHandle<Output>
 	where
 		E: Environment,
 		T: for<'ctx> Task<Output<'ctx> = Output>
@@ -261,7 +357,13 @@ impl<Output> Spawn<Output> {
 		let handle = unsafe { this.handle.as_mut() };
 
 		/* Safety: guaranteed by caller */
-		match unsafe { spawn_task_with_env(env, task).run(ptr!(&this.request)) } {
+		let mut env = unsafe { clone_env(env, name) };
+
+		/* Safety: the spawn is kept alive until the task completes */
+		unsafe { call_no_unwind(|| env.context_mut()).set_reason_source(ptr!(&this.reason)) };
+
+		/* Safety: guaranteed by caller */
+		match unsafe { spawn_task_with_hint(env, task, hint).run(ptr!(&this.request)) } {
 			Progress::Done(result) => handle.output = Some(result),
 			Progress::Pending(cancel) => {
 				handle.cancel = Some(cancel);
@@ -282,6 +384,18 @@ impl<Output> Pin for Spawn<Output> {
 	}
 }
 
+impl<Output> ScopeChild for Spawn<Output> {
+	fn is_running(&self) -> bool {
+		/* Safety: exclusive unsafe cell access */
+		unsafe { self.handle.as_ref() }.cancel.is_some()
+	}
+
+	fn request_cancel(&self) -> Result<()> {
+		/* Safety: the cancel is cleared when the task completes */
+		unsafe { self.try_cancel(None) }.unwrap_or(Ok(()))
+	}
+}
+
 /// A handle for joining with a [`fn@spawn`]ed async task
 pub struct JoinHandle<Output> {
 	task: Rc<Spawn<Output>>
@@ -299,9 +413,9 @@ impl<Output> JoinHandle<Output> {
 
 	/// # Safety
 	/// future must be in progress
-	unsafe fn try_cancel(&self) -> Result<()> {
+	unsafe fn try_cancel(&self, reason: Option<InterruptReason>) -> Result<()> {
 		/* Safety: guaranteed by caller */
-		unsafe { self.handle().try_cancel() }.unwrap_or(Ok(()))
+		unsafe { self.task.try_cancel(reason) }.unwrap_or(Ok(()))
 	}
 
 	#[future]
@@ -309,7 +423,7 @@ impl<Output> JoinHandle<Output> {
 		#[cancel]
 		fn cancel(self) -> Result<()> {
 			/* Safety: guaranteed by Future's contract. we may already be cancelling */
-			unsafe { self.try_cancel() }
+			unsafe { self.try_cancel(None) }
 		}
 
 		/* Safety: exclusive unsafe cell access */
@@ -329,11 +443,19 @@ impl<Output> JoinHandle<Output> {
 
 	/// Signals the task to cancel, without waiting for the result
 	pub fn request_cancel(&self) -> Result<()> {
+		self.request_cancel_with(None)
+	}
+
+	/// Same as [`JoinHandle::request_cancel`], with a reason that the task
+	/// sees in the errors caused by the interrupt
+	///
+	/// See [`Context::interrupt_with`]
+	pub fn request_cancel_with(&self, reason: Option<InterruptReason>) -> Result<()> {
 		if self.is_done() {
 			Ok(())
 		} else {
 			/* Safety: task is running */
-			unsafe { self.try_cancel() }
+			unsafe { self.try_cancel(reason) }
 		}
 	}
 
@@ -359,6 +481,31 @@ impl<Output> JoinHandle<Output> {
 	}
 }
 
+impl<Output> JoinHandle<Output> {
+	/// Notify `scope` when the task completes. Returns `None` if the task
+	/// has already completed
+	///
+	/// # Safety
+	/// `scope` must be valid until the task completes
+	pub(super) unsafe fn attach_scope<'a>(
+		&self, scope: Ptr<ScopeCounter>
+	) -> Option<Rc<dyn ScopeChild + 'a>>
+	where
+		Output: 'a
+	{
+		/* Safety: exclusive unsafe cell access */
+		let handle = unsafe { self.handle() };
+
+		if handle.cancel.is_none() {
+			return None;
+		}
+
+		handle.scope = scope;
+
+		Some(self.task.clone())
+	}
+}
+
 #[asynchronous(task)]
 impl<Output> Task for JoinHandle<Output> {
 	type Output = Output;
@@ -376,15 +523,54 @@ impl<Output> Task for JoinHandle<Output> {
 ///
 /// # Safety
 /// The cloned `env` and `task` must outlive the spawned fiber
+#[track_caller]
 pub unsafe fn spawn<E, T, Output>(env: &E, task: T) -> JoinHandle<Output>
 where
 	E: Environment,
 	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	/* Safety: guaranteed by caller */
+	unsafe { spawn_with_hint(env, task, StackHint::Default) }
+}
+
+/// Spawn a new async task with a stack of the size in `hint`
+///
+/// Small stacks let many lightweight tasks, such as connection handlers, be
+/// held at once. Large stacks suit deeply recursive tasks
+///
+/// # Safety
+/// The cloned `env` and `task` must outlive the spawned fiber
+#[track_caller]
+pub unsafe fn spawn_with_hint<E, T, Output>(env: &E, task: T, hint: StackHint) -> JoinHandle<Output>
+where
+	E: Environment,
+	T: for<'ctx> Task<Output<'ctx> = Output>
+{
+	#[cfg(any(doc, feature = "xx-doc"))]
+	unreachable!();
+
+	#[cfg(not(any(doc, feature = "xx-doc")))]
+	/* Safety: guaranteed by caller */
+	(unsafe { Spawn::run(env, task, hint, None) })
+}
+
+/// Spawn a new async task with a name, which is shown in task dumps
+///
+/// See [`TaskInfo`] and [`Executor::dump_tasks`]
+///
+/// # Safety
+/// The cloned `env` and `task` must outlive the spawned fiber
+#[track_caller]
+pub unsafe fn spawn_named<E, N, T, Output>(env: &E, name: N, task: T) -> JoinHandle<Output>
+where
+	E: Environment,
+	N: Into<Cow<'static, str>>,
+	T: for<'ctx> Task<Output<'ctx> = Output>
 {
 	#[cfg(any(doc, feature = "xx-doc"))]
 	unreachable!();
 
 	#[cfg(not(any(doc, feature = "xx-doc")))]
 	/* Safety: guaranteed by caller */
-	(unsafe { Spawn::run(env, task) })
+	(unsafe { Spawn::run(env, task, StackHint::Default, Some(name.into())) })
 }
@@ -1,4 +1,9 @@
+use std::os::fd::BorrowedFd;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
 use super::*;
+use crate::macros::panic_nounwind;
+use crate::os::eventfd::{CreateFlag, EventFd};
 
 #[derive(Clone, Copy)]
 pub struct WakerVTable {
@@ -45,3 +50,163 @@ impl Waker {
 		unsafe { (self.vtable.wake)(self.ptr, request) }
 	}
 }
+
+struct Handoff {
+	request: ReqPtr<()>,
+	next: MutPtr<Handoff>
+}
+
+/// A [`Waker`] that hands completed requests back to the thread that owns it
+/// through an [`EventFd`]
+///
+/// Other threads push requests onto a lock-free queue, and signal the eventfd
+/// if the queue was empty. The owning thread polls [`fd`] for readability in
+/// its event loop, then calls [`run_pending`] to complete the requests.
+///
+/// This is a building block for runtimes: nothing in this crate polls the
+/// eventfd or creates contexts with this waker. The runtime must call
+/// [`run_pending`] until every context using the waker has finished before
+/// dropping it. Dropping it with requests still queued aborts the process,
+/// since the tasks waiting on them could never be resumed.
+///
+/// [`fd`]: EventFdWaker::fd
+/// [`run_pending`]: EventFdWaker::run_pending
+pub struct EventFdWaker {
+	event: EventFd,
+	queue: AtomicPtr<Handoff>
+}
+
+impl EventFdWaker {
+	/* Safety: prepare and wake never unwind, and wake is thread safe */
+	const VTABLE: WakerVTable = unsafe { WakerVTable::new(Self::prepare, Self::wake) };
+
+	pub fn new() -> OsResult<Self> {
+		let event = EventFd::new(CreateFlag::NonBlock | CreateFlag::CloseOnExec)?;
+
+		Ok(Self {
+			event,
+			queue: AtomicPtr::new(MutPtr::null().as_mut_ptr())
+		})
+	}
+
+	/// The eventfd that becomes readable when requests are pending
+	#[must_use]
+	pub fn fd(&self) -> BorrowedFd<'_> {
+		self.event.fd()
+	}
+
+	/// # Safety
+	/// `self` must not move, and must outlive every context using the
+	/// returned waker
+	#[must_use]
+	pub unsafe fn waker(&self) -> Waker {
+		Waker::new(ptr!(self).cast(), &Self::VTABLE)
+	}
+
+	/// # Safety
+	/// Nothing to uphold
+	unsafe fn prepare(_: Ptr<()>) {}
+
+	/// # Safety
+	/// `this` must be a valid `EventFdWaker`
+	unsafe fn wake(this: Ptr<()>, request: ReqPtr<()>) {
+		/* Safety: guaranteed by caller */
+		let this = unsafe { this.cast::<Self>().as_ref() };
+
+		this.push(request);
+	}
+
+	fn push(&self, request: ReqPtr<()>) {
+		let node = MutPtr::from(Box::into_raw(Box::new(Handoff {
+			request,
+			next: MutPtr::null()
+		})));
+
+		let mut head = self.queue.load(Ordering::Relaxed);
+
+		loop {
+			/* Safety: the node is not shared until the exchange succeeds */
+			unsafe { ptr!(node=>next) = MutPtr::from(head) };
+
+			match self.queue.compare_exchange_weak(
+				head,
+				node.as_mut_ptr(),
+				Ordering::Release,
+				Ordering::Relaxed
+			) {
+				Ok(_) => break,
+				Err(current) => head = current
+			}
+		}
+
+		/* the owner drained the queue, so it needs to be signalled again */
+		if !head.is_null() {
+			return;
+		}
+
+		if let Err(err) = self.event.write(1) {
+			warn!(target: self, "== Failed to signal eventfd: {:?}", err);
+		}
+	}
+
+	fn take_all(&self) -> MutPtr<Handoff> {
+		let mut node = MutPtr::from(
+			self.queue
+				.swap(MutPtr::null().as_mut_ptr(), Ordering::Acquire)
+		);
+		let mut reversed = MutPtr::null();
+
+		/* the queue is a stack, reverse it to complete requests in order */
+		while !node.is_null() {
+			/* Safety: we own all the nodes */
+			let next = unsafe { ptr!(node=>next) };
+
+			/* Safety: same as above */
+			unsafe { ptr!(node=>next) = reversed };
+
+			reversed = node;
+			node = next;
+		}
+
+		reversed
+	}
+
+	/// Complete all requests handed off by other threads, returning the
+	/// number completed
+	///
+	/// # Safety
+	/// Must be called on the thread that owns the contexts using this waker,
+	/// outside of any async task
+	pub unsafe fn run_pending(&self) -> usize {
+		/* clear the eventfd before taking the queue, so that a request pushed
+		 * after the swap always signals it again
+		 */
+		let _ = self.event.read();
+
+		let mut node = self.take_all();
+		let mut count = 0;
+
+		while !node.is_null() {
+			/* Safety: the node was allocated by `push` */
+			let Handoff { request, next } = *unsafe { Box::from_raw(node.as_mut_ptr()) };
+
+			/* Safety: guaranteed by caller */
+			unsafe { Request::complete(request, ()) };
+
+			node = next;
+
+			#[allow(clippy::arithmetic_side_effects)]
+			(count += 1);
+		}
+
+		count
+	}
+}
+
+impl Drop for EventFdWaker {
+	fn drop(&mut self) {
+		if !self.take_all().is_null() {
+			panic_nounwind!("EventFdWaker dropped with pending requests");
+		}
+	}
+}
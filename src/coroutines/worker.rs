@@ -55,6 +55,10 @@ impl Worker {
 		self.caller.set(to);
 	}
 
+	pub(super) const fn executor(&self) -> Ptr<Executor> {
+		self.executor
+	}
+
 	pub(super) fn fiber(&self) -> MutPtr<Fiber> {
 		self.fiber.get()
 	}
@@ -0,0 +1,293 @@
+use std::sync::OnceLock;
+
+use super::*;
+
+/// The smallest stack size a [`StackHint`] resolves to (16 KiB)
+pub const MIN_STACK_SIZE: usize = 0x4000;
+
+/// The stack size of [`StackHint::Small`] (32 KiB)
+pub const SMALL_STACK_SIZE: usize = 0x8000;
+
+/// The stack size of [`StackHint::Large`] (4 MiB)
+pub const LARGE_STACK_SIZE: usize = 0x40_0000;
+
+/// The stack size a spawned task needs
+///
+/// Hints are rounded up to a power of two, so that fibers of similar sizes
+/// share a pool bucket
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StackHint {
+	/// The default stack size of the executor's pool, or `RLIMIT_STACK`
+	#[default]
+	Default,
+
+	/// A small stack, for tasks that don't recurse or keep large buffers on
+	/// the stack, such as connection handlers
+	Small,
+
+	/// A large stack, for deeply recursive tasks such as parsers
+	Large,
+
+	/// At least this many bytes of stack
+	Size(usize)
+}
+
+impl StackHint {
+	/// The stack size this hint resolves to, or `None` for the default
+	#[must_use]
+	pub const fn class(self) -> Option<usize> {
+		let size = match self {
+			Self::Default => return None,
+			Self::Small => SMALL_STACK_SIZE,
+			Self::Large => LARGE_STACK_SIZE,
+			Self::Size(size) => size
+		};
+
+		if size <= MIN_STACK_SIZE {
+			return Some(MIN_STACK_SIZE);
+		}
+
+		match size.checked_next_power_of_two() {
+			Some(size) => Some(size),
+			None => Some(size)
+		}
+	}
+}
+
+/// The size of the main thread's stack, used for fibers unless specified
+/// otherwise
+fn default_stack_size() -> OsResult<usize> {
+	static SIZE: OnceLock<usize> = OnceLock::new();
+
+	if let Some(size) = SIZE.get() {
+		return Ok(*size);
+	}
+
+	let size = get_limit(Resource::Stack)?.try_into().unwrap_or(usize::MAX);
+
+	Ok(*SIZE.get_or_init(|| size))
+}
+
+/// Configures how a fiber's stack is allocated
+///
+/// # Examples
+///
+/// ```
+/// let fiber = FiberBuilder::new()
+/// 	.stack_size(64 * 1024)
+/// 	.guard_pages(2)
+/// 	.no_reserve(true)
+/// 	.build()?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FiberBuilder {
+	stack_size: Option<usize>,
+	initial: Option<usize>,
+	guard_pages: usize,
+	no_reserve: bool,
+	huge_pages: bool,
+	locked: bool
+}
+
+impl FiberBuilder {
+	/// A builder for fibers with a stack of `RLIMIT_STACK` bytes and one
+	/// guard page
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			stack_size: None,
+			initial: None,
+			guard_pages: 1,
+			no_reserve: false,
+			huge_pages: false,
+			locked: false
+		}
+	}
+
+	/// Set the usable size of the stack, excluding guard pages. Rounded up
+	/// to the page size
+	#[must_use]
+	pub const fn stack_size(mut self, size: usize) -> Self {
+		self.stack_size = Some(size);
+		self
+	}
+
+	/// Set the number of inaccessible pages below the stack, which catch
+	/// overflows. Defaults to one
+	///
+	/// A function with a frame larger than the guard region may skip over it.
+	/// Growable stacks grow by faulting on their guard region, so they always
+	/// have at least one guard page
+	#[must_use]
+	pub const fn guard_pages(mut self, count: usize) -> Self {
+		self.guard_pages = count;
+		self
+	}
+
+	/// Set whether the stack is committed up front or grows on demand
+	#[must_use]
+	pub const fn stack_mode(mut self, mode: StackMode) -> Self {
+		match mode {
+			StackMode::Fixed => self.initial = None,
+			StackMode::Growable { initial, max } => {
+				self.initial = Some(initial);
+				self.stack_size = Some(max);
+			}
+		}
+
+		self
+	}
+
+	/// The stack mode of fibers created by this builder
+	#[must_use]
+	pub const fn mode(&self) -> StackMode {
+		match (self.initial, self.stack_size) {
+			(Some(initial), Some(max)) => StackMode::Growable { initial, max },
+			_ => StackMode::Fixed
+		}
+	}
+
+	/// Apply the size from `hint`, if it isn't [`StackHint::Default`]
+	#[must_use]
+	pub const fn stack_hint(mut self, hint: StackHint) -> Self {
+		if let Some(size) = hint.class() {
+			self.stack_size = Some(size);
+		}
+
+		self
+	}
+
+	/// Don't reserve swap space for the stack (`MAP_NORESERVE`). Pages
+	/// touched when memory is exhausted raise `SIGSEGV` instead of failing
+	/// the allocation. Growable stacks are always mapped this way
+	#[must_use]
+	pub const fn no_reserve(mut self, no_reserve: bool) -> Self {
+		self.no_reserve = no_reserve;
+		self
+	}
+
+	/// Ask for the stack to be backed by transparent huge pages. Only
+	/// worthwhile for stacks several megabytes large
+	#[must_use]
+	pub const fn huge_pages(mut self, huge_pages: bool) -> Self {
+		self.huge_pages = huge_pages;
+		self
+	}
+
+	/// Lock the stack into memory, so that it is never swapped out. Growable
+	/// stacks lock pages as they are committed
+	#[must_use]
+	pub const fn locked(mut self, locked: bool) -> Self {
+		self.locked = locked;
+		self
+	}
+
+	/// The usable size of stacks created by this builder, rounded up to the
+	/// page size
+	pub fn resolved_stack_size(&self) -> OsResult<usize> {
+		let size = match self.stack_size {
+			Some(size) => size,
+			None => default_stack_size()?
+		};
+
+		let page_size = page_size();
+
+		size.checked_next_multiple_of(page_size)
+			.filter(|size| *size > 0)
+			.ok_or(OsError::Inval)
+	}
+
+	/// Allocate a fiber
+	///
+	/// The fiber must have its entry point set with [`Fiber::set_start`]
+	/// before it is switched to
+	pub fn build(&self) -> OsResult<Fiber> {
+		let page_size = page_size();
+		let size = self.resolved_stack_size()?;
+		let guard_pages = match self.initial {
+			Some(_) => self.guard_pages.max(1),
+			None => self.guard_pages
+		};
+
+		let guard = guard_pages.checked_mul(page_size).ok_or(OsError::NoMem)?;
+		let len = size.checked_add(guard).ok_or(OsError::NoMem)?;
+
+		let mut flags = BitFlags::from(Flag::Anonymous) | Flag::Stack;
+		let mut protection = BitFlags::default();
+
+		if self.no_reserve || self.initial.is_some() {
+			flags |= Flag::NoReserve;
+		}
+
+		if self.initial.is_none() {
+			protection = Protection::Read | Protection::Write;
+		}
+
+		let stack = Builder::new(Type::Private, len)
+			.protect(protection)
+			.flag(flags)
+			.map()?;
+
+		let growth = match self.initial {
+			Some(initial) => {
+				let initial = initial
+					.max(1)
+					.checked_next_multiple_of(page_size)
+					.ok_or(OsError::Inval)?;
+
+				Some(Growth::new(&stack, guard, initial)?)
+			}
+
+			None if guard != 0 => {
+				/* Safety: map the bottom `guard` bytes as guard pages */
+				unsafe {
+					mprotect(
+						RawBuf::from_parts(stack.as_ptr().cast_const(), guard),
+						Default::default()
+					)?;
+				}
+
+				None
+			}
+
+			None => None
+		};
+
+		if self.huge_pages {
+			/* not supported by every kernel, which is harmless
+			 * Safety: doesn't modify the contents or permissions */
+			let _ = unsafe { stack.advise(Advice::HugePage) };
+		}
+
+		if self.locked {
+			/* Safety: the stack is ours */
+			unsafe { stack.lock() }?;
+		}
+
+		let bounds = Box::new(Bounds::new(&stack, guard, growth));
+
+		Ok(Fiber {
+			context: Context::default(),
+			stack,
+			guard,
+			bounds: Some(bounds),
+			home: None
+		})
+	}
+
+	/// Allocate a fiber with the entry point `start`
+	pub fn build_with_start(&self, start: Start) -> OsResult<Fiber> {
+		let mut fiber = self.build()?;
+
+		/* Safety: the fiber was never started */
+		unsafe { fiber.set_start(start) };
+
+		Ok(fiber)
+	}
+}
+
+impl Default for FiberBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
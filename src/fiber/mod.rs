@@ -2,7 +2,11 @@
 
 use std::arch::global_asm;
 use std::mem::{zeroed, ManuallyDrop};
+use std::sync::Arc;
 
+use enumflags2::BitFlags;
+
+use crate::error::{OsError, OsResult};
 use crate::macros::{assert_unsafe_precondition, import_sysdeps};
 use crate::opt::hint::unreachable_unchecked;
 use crate::os::mman::*;
@@ -11,10 +15,16 @@ use crate::os::unistd::*;
 use crate::os::RawBuf;
 use crate::pointer::*;
 
+mod builder;
 pub mod pool;
+mod stack;
 
+pub use builder::*;
+use pool::ReturnStack;
 #[doc(inline)]
 pub use pool::*;
+use stack::*;
+pub use stack::{install_overflow_handler, StackMode};
 
 import_sysdeps!();
 
@@ -106,32 +116,37 @@ unsafe extern "C" fn exit_fiber(arg: Ptr<()>) {
 unsafe extern "C" fn exit_fiber_to_pool(arg: Ptr<()>) {
 	/* Safety: guaranteed by caller */
 	let arg = unsafe {
-		arg.cast::<(ManuallyDrop<Fiber>, Ptr<Pool>)>()
+		arg.cast::<(ManuallyDrop<Fiber>, Ptr<LocalPool>)>()
 			.cast_mut()
 			.as_mut()
 	};
 
 	/* Safety: ownership of the fiber is passed to us */
-	let mut fiber = unsafe { ManuallyDrop::take(&mut arg.0) };
-
-	/* Safety: guaranteed by caller */
-	unsafe {
-		fiber.clear_stack();
+	let fiber = unsafe { ManuallyDrop::take(&mut arg.0) };
 
-		ptr!(arg.1=>exit_fiber(fiber));
-	}
+	/* Safety: guaranteed by caller. the pool clears the stack */
+	unsafe { ptr!(arg.1=>exit_fiber(fiber)) };
 }
 
 #[cfg_attr(not(any(doc, feature = "xx-doc")), repr(C))]
 pub struct Fiber {
 	context: Context,
-	stack: Map<'static>
+	stack: Map<'static>,
+	guard: usize,
+	bounds: Option<Box<Bounds>>,
+	home: Option<Arc<ReturnStack>>
 }
 
 impl Fiber {
 	#[must_use]
 	pub fn main() -> Self {
-		Self { context: Context::default(), stack: Map::new() }
+		Self {
+			context: Context::default(),
+			stack: Map::new(),
+			guard: 0,
+			bounds: None,
+			home: None
+		}
 	}
 
 	#[allow(clippy::new_without_default, clippy::expect_used)]
@@ -139,40 +154,9 @@ impl Fiber {
 	/// # Panics
 	/// If the stack allocation fails
 	pub fn new() -> Self {
-		let stack_size = get_limit(Resource::Stack)
-			.expect("Failed to get stack size")
-			.try_into()
-			.expect("Valid stack size");
-		let page_size = get_system_configuration(SystemConfiguration::Pagesize)
-			.expect("Failed to get page size")
-			.unwrap_or(0)
-			.try_into()
-			.expect("Valid page size");
-
-		assert!(page_size > 0 && stack_size > 0 && stack_size > page_size);
-
-		let stack = Builder::new(Type::Private, stack_size)
-			.protect(Protection::Read | Protection::Write)
-			.flag(Flag::Anonymous | Flag::Stack)
-			.map()
-			.expect("Failed to allocate stack for fiber");
-
-		/* Safety: map the bottom `page_size` bytes as a guard page */
-		unsafe {
-			mprotect(
-				RawBuf::from_parts(stack.as_ptr().cast_const(), page_size),
-				Default::default()
-			)
-			.expect("Failed to set permissions for guard page");
-		}
-
-		Self {
-			/* fiber context. stores the current stack and instruction pointer registers,
-			 * and any that cannot be corrupted by inline asm
-			 */
-			context: Context::default(),
-			stack
-		}
+		FiberBuilder::new()
+			.build()
+			.expect("Failed to allocate stack for fiber")
 	}
 
 	#[must_use]
@@ -185,6 +169,60 @@ impl Fiber {
 		this
 	}
 
+	/// Create a fiber whose stack starts at `initial` bytes and grows on demand
+	/// up to `max` bytes
+	///
+	/// Address space for `max` bytes is reserved up front, but memory is only
+	/// committed as the stack grows. Overflowing `max` aborts the process
+	///
+	/// # Panics
+	/// If the stack allocation fails
+	#[allow(clippy::expect_used)]
+	#[must_use]
+	pub fn new_growable(initial: usize, max: usize) -> Self {
+		FiberBuilder::new()
+			.stack_mode(StackMode::Growable { initial, max })
+			.build()
+			.expect("Failed to allocate stack for fiber")
+	}
+
+	#[must_use]
+	pub fn new_growable_with_start(initial: usize, max: usize, start: Start) -> Self {
+		let mut this = Self::new_growable(initial, max);
+
+		/* Safety: the fiber was never started */
+		unsafe { this.set_start(start) };
+
+		this
+	}
+
+	/// Create a fiber with a stack allocated according to `mode`
+	///
+	/// # Panics
+	/// If the stack allocation fails
+	#[allow(clippy::expect_used)]
+	#[must_use]
+	pub fn new_with_mode(mode: StackMode, start: Start) -> Self {
+		FiberBuilder::new()
+			.stack_mode(mode)
+			.build_with_start(start)
+			.expect("Failed to allocate stack for fiber")
+	}
+
+	/// The usable size of the stack in bytes, excluding guard pages. For
+	/// growable stacks, this is the maximum size
+	#[must_use]
+	#[allow(clippy::arithmetic_side_effects)]
+	pub fn stack_size(&self) -> usize {
+		self.stack.len() - self.guard
+	}
+
+	/// Returns `true` if the fiber's stack grows on demand
+	#[must_use]
+	pub fn is_growable(&self) -> bool {
+		self.growth().is_some()
+	}
+
 	/// Set the entry point of the fiber
 	///
 	/// # Safety
@@ -218,6 +256,12 @@ impl Fiber {
 		 * store them for us
 		 */
 
+		/* let the fault handler know which stack may grow or overflow
+		 *
+		 * Safety: guaranteed by caller
+		 */
+		unsafe { set_current(ptr!(to=>bounds.as_deref())) };
+
 		/* Safety: guaranteed by caller */
 		unsafe { switch(ptr!(&mut this=>context), ptr!(&mut to=>context)) };
 	}
@@ -227,6 +271,11 @@ impl Fiber {
 	pub unsafe fn clear_stack(&mut self) {
 		/* Safety: fiber isn't running */
 		let _ = unsafe { self.stack.advise(Advice::Free) };
+
+		if let Some(growth) = self.growth() {
+			/* Safety: fiber isn't running */
+			unsafe { growth.reset() };
+		}
 	}
 
 	/// Same as switch, except drops the `self` fiber
@@ -263,7 +312,7 @@ impl Fiber {
 	///
 	/// # Safety
 	/// same as above
-	pub unsafe fn exit_to_pool(self, to: MutPtr<Self>, pool: Ptr<Pool>) -> ! {
+	pub unsafe fn exit_to_pool(self, to: MutPtr<Self>, pool: Ptr<LocalPool>) -> ! {
 		/* Safety: guaranteed by caller */
 		unsafe { assert_unsafe_precondition!(!to.is_null()) };
 
@@ -1,95 +1,600 @@
-use std::sync::Mutex;
+//! Fiber stack pooling
+//!
+//! A [`Pool`] is shared by every thread. It holds exited fibers behind a lock,
+//! and keeps the statistics.
+//!
+//! Each executor puts a [`LocalPool`] in front of it. That is a per thread
+//! cache with no locking, which only falls back to the shared pool when it is
+//! empty or full. Fibers remember the local pool that handed them out, and
+//! fibers that exit on another thread are returned to it through a lock free
+//! stack, which the owner drains on its next allocation.
+//!
+//! Statistics are kept by each local pool, so that its fast path doesn't
+//! contend with other threads, and summed when [`Pool::stats`] is called.
+//! Only the active fiber count is shared, as the policy needs it.
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::cell::{Cell, UnsafeCell};
 use crate::fiber::*;
 use crate::impls::OptionExt;
+use crate::sync::atomic::AtomicMutPtr;
 use crate::trace;
 
-struct Data {
-	pool: Vec<Fiber>,
-	active: u64
+/// The number of buckets in [`PoolStats::high_water`]
+pub const HIGH_WATER_BUCKETS: usize = 16;
+
+/// The upper bound of the first bucket in [`PoolStats::high_water`] (4 KiB)
+pub const HIGH_WATER_BASE: usize = 0x1000;
+
+/// Controls how many exited fibers a [`Pool`] keeps for reuse
+///
+/// The pool keeps up to `ratio`% of the active fiber count plus `base`
+/// fibers, but never more than `max`. The default is 20% + 16, without a
+/// maximum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolPolicy {
+	ratio: u64,
+	base: u64,
+	max: u64,
+	idle: u64,
+	paint_stacks: bool
+}
+
+impl PoolPolicy {
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			ratio: 20,
+			base: 16,
+			max: u64::MAX,
+			idle: 16,
+			paint_stacks: false
+		}
+	}
+
+	/// Keep `percent`% of the number of active fibers
+	#[must_use]
+	pub const fn ratio(mut self, percent: u64) -> Self {
+		self.ratio = percent;
+		self
+	}
+
+	/// Keep this many fibers in addition to the ratio
+	#[must_use]
+	pub const fn base(mut self, count: u64) -> Self {
+		self.base = count;
+		self
+	}
+
+	/// Never keep more than `count` fibers
+	#[must_use]
+	pub const fn max(mut self, count: u64) -> Self {
+		self.max = count;
+		self
+	}
+
+	/// Keep this many fibers when [trimming](Pool::trim)
+	#[must_use]
+	pub const fn idle(mut self, count: u64) -> Self {
+		self.idle = count;
+		self
+	}
+
+	/// Measure the [high water](Fiber::stack_high_water) of each stack when
+	/// its fiber exits, recording it in [`PoolStats::high_water`]
+	///
+	/// Pooled stacks are zeroed instead of freed lazily, and each exit reads
+	/// the whole stack, so this is costly
+	#[must_use]
+	pub const fn paint_stacks(mut self, paint: bool) -> Self {
+		self.paint_stacks = paint;
+		self
+	}
+
+	/// The number of fibers to keep when `active` fibers are running
+	#[must_use]
+	pub const fn ideal(&self, active: u64) -> u64 {
+		let ideal = active
+			.saturating_mul(self.ratio)
+			.saturating_div(100)
+			.saturating_add(self.base);
+
+		if ideal < self.max {
+			ideal
+		} else {
+			self.max
+		}
+	}
+}
+
+impl Default for PoolPolicy {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Counters describing the use of a [`Pool`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+	/// Fibers allocated, including by [`Pool::prewarm`]
+	pub created: u64,
+
+	/// Fibers taken from the pool instead of being allocated
+	pub reused: u64,
+
+	/// Exited or trimmed fibers that were freed instead of kept
+	pub dropped: u64,
+
+	/// Fibers currently running
+	pub active: u64,
+
+	/// The most fibers that were running at once
+	pub peak_active: u64,
+
+	/// Fibers currently kept for reuse, in the shared pool, local pools, and
+	/// on their way back to local pools from other threads
+	pub pooled: u64,
+
+	/// A histogram of stack high water marks, if
+	/// [painting](PoolPolicy::paint_stacks) is enabled
+	///
+	/// Bucket `i` counts fibers whose stack use was at most
+	/// `HIGH_WATER_BASE << i` bytes, and the last bucket counts the rest
+	pub high_water: [u64; HIGH_WATER_BUCKETS]
+}
+
+/// The counters of [`PoolStats`], updated without locking
+///
+/// A local pool's counters are only written by its own thread, so they are
+/// updated without atomic read-modify-writes. They may wrap below zero, as
+/// fibers cached by one pool can be taken by another, but their sum with the
+/// shared counters is correct
+struct Counters {
+	/// Written only by the owning thread
+	owned: bool,
+	created: AtomicU64,
+	reused: AtomicU64,
+	dropped: AtomicU64,
+	active: AtomicU64,
+	peak_active: AtomicU64,
+	pooled: AtomicU64,
+	high_water: [AtomicU64; HIGH_WATER_BUCKETS]
+}
+
+impl Counters {
+	#[allow(clippy::declare_interior_mutable_const)]
+	const ZERO: AtomicU64 = AtomicU64::new(0);
+
+	const fn new(owned: bool) -> Self {
+		Self {
+			owned,
+			created: AtomicU64::new(0),
+			reused: AtomicU64::new(0),
+			dropped: AtomicU64::new(0),
+			active: AtomicU64::new(0),
+			peak_active: AtomicU64::new(0),
+			pooled: AtomicU64::new(0),
+			high_water: [Self::ZERO; HIGH_WATER_BUCKETS]
+		}
+	}
+
+	fn add(&self, counter: &AtomicU64, count: u64) {
+		if self.owned {
+			let value = counter.load(Ordering::Relaxed).wrapping_add(count);
+
+			counter.store(value, Ordering::Relaxed);
+		} else {
+			counter.fetch_add(count, Ordering::Relaxed);
+		}
+	}
+
+	fn sub(&self, counter: &AtomicU64, count: u64) {
+		self.add(counter, count.wrapping_neg());
+	}
+
+	fn start(&self) {
+		let active = self
+			.active
+			.fetch_add(1, Ordering::Relaxed)
+			.checked_add(1)
+			.expect_nounwind("Fatal error: fiber count overflow");
+
+		/* only write the peak when it changes */
+		if active > self.peak_active.load(Ordering::Relaxed) {
+			self.peak_active.fetch_max(active, Ordering::Relaxed);
+		}
+	}
+
+	#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+	fn record_high_water(&self, bytes: usize) {
+		let index = bytes
+			.div_ceil(HIGH_WATER_BASE)
+			.max(1)
+			.next_power_of_two()
+			.trailing_zeros() as usize;
+
+		self.add(&self.high_water[index.min(HIGH_WATER_BUCKETS - 1)], 1);
+	}
+
+	/// Add these counters to `stats`
+	fn accumulate(&self, stats: &mut PoolStats) {
+		let add = |total: &mut u64, counter: &AtomicU64| {
+			*total = total.wrapping_add(counter.load(Ordering::Relaxed));
+		};
+
+		add(&mut stats.created, &self.created);
+		add(&mut stats.reused, &self.reused);
+		add(&mut stats.dropped, &self.dropped);
+		add(&mut stats.active, &self.active);
+		add(&mut stats.peak_active, &self.peak_active);
+		add(&mut stats.pooled, &self.pooled);
+
+		for (total, counter) in stats.high_water.iter_mut().zip(&self.high_water) {
+			add(total, counter);
+		}
+	}
+
+	/// Move these counters into the shared counters `to`
+	fn merge_into(&self, to: &Self) {
+		let merge = |from: &AtomicU64, to: &AtomicU64| {
+			to.fetch_add(from.swap(0, Ordering::Relaxed), Ordering::Relaxed);
+		};
+
+		merge(&self.created, &to.created);
+		merge(&self.reused, &to.reused);
+		merge(&self.dropped, &to.dropped);
+		merge(&self.pooled, &to.pooled);
+
+		for (from, to) in self.high_water.iter().zip(&to.high_water) {
+			merge(from, to);
+		}
+	}
+}
+
+/// Pooled fibers with the same stack size
+struct Bucket {
+	stack_size: usize,
+	fibers: Vec<Fiber>
+}
+
+/// Pooled fibers, grouped by stack size
+struct Buckets {
+	buckets: Vec<Bucket>,
+	len: u64
 }
 
-impl Data {
+impl Buckets {
 	const fn new() -> Self {
-		Self { pool: Vec::new(), active: 0 }
+		Self { buckets: Vec::new(), len: 0 }
+	}
+
+	fn pop(&mut self, stack_size: usize) -> Option<Fiber> {
+		let fiber = self
+			.buckets
+			.iter_mut()
+			.find(|bucket| bucket.stack_size == stack_size)
+			.and_then(|bucket| bucket.fibers.pop());
+
+		if fiber.is_some() {
+			#[allow(clippy::arithmetic_side_effects)]
+			(self.len -= 1);
+		}
+
+		fiber
+	}
+
+	/// Keep `fiber`, returning it back if out of memory
+	fn push(&mut self, fiber: Fiber) -> Option<Fiber> {
+		let stack_size = fiber.stack_size();
+		let bucket = match self
+			.buckets
+			.iter()
+			.position(|bucket| bucket.stack_size == stack_size)
+		{
+			Some(index) => index,
+			None => {
+				if self.buckets.try_reserve(1).is_err() {
+					return Some(fiber);
+				}
+
+				self.buckets.push(Bucket { stack_size, fibers: Vec::new() });
+
+				#[allow(clippy::arithmetic_side_effects)]
+				(self.buckets.len() - 1)
+			}
+		};
+
+		#[allow(clippy::indexing_slicing)]
+		let fibers = &mut self.buckets[bucket].fibers;
+
+		if fibers.try_reserve(1).is_err() {
+			return Some(fiber);
+		}
+
+		fibers.push(fiber);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.len += 1);
+
+		None
+	}
+
+	/// Remove fibers until at most `keep` remain, starting with the largest
+	/// stacks
+	fn trim(&mut self, keep: u64) -> Vec<Fiber> {
+		let mut excess = self.len.saturating_sub(keep);
+		let mut trimmed = Vec::new();
+
+		self.buckets
+			.sort_unstable_by(|a, b| b.stack_size.cmp(&a.stack_size));
+
+		for bucket in &mut self.buckets {
+			#[allow(clippy::cast_possible_truncation)]
+			let count = excess.min(bucket.fibers.len() as u64) as usize;
+
+			#[allow(clippy::arithmetic_side_effects)]
+			let remain = bucket.fibers.len() - count;
+
+			trimmed.extend(bucket.fibers.drain(remain..));
+
+			#[allow(clippy::arithmetic_side_effects)]
+			(excess -= count as u64);
+		}
+
+		self.buckets.retain(|bucket| !bucket.fibers.is_empty());
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.len -= trimmed.len() as u64);
+
+		trimmed
 	}
 }
 
 pub struct Pool {
-	data: Mutex<Data>
+	shared: Mutex<Buckets>,
+	counters: Counters,
+
+	/* the counters of each local pool */
+	locals: Mutex<Vec<Arc<Counters>>>,
+	builder: FiberBuilder,
+	policy: PoolPolicy
 }
 
 impl Pool {
 	#[must_use]
 	pub const fn new() -> Self {
-		Self { data: Mutex::new(Data::new()) }
+		Self::with_builder(FiberBuilder::new())
 	}
 
-	/// # Panics
-	/// if creating a fiber fails
+	/// Create a pool whose fibers have stacks allocated according to `mode`
 	#[must_use]
-	pub fn new_fiber(&self, start: Start) -> Fiber {
-		let fiber = {
+	pub const fn with_stack_mode(mode: StackMode) -> Self {
+		Self::with_builder(FiberBuilder::new().stack_mode(mode))
+	}
+
+	/// Create a pool whose fibers are allocated by `builder`. Stack hints
+	/// override the builder's stack size
+	#[must_use]
+	pub const fn with_builder(builder: FiberBuilder) -> Self {
+		Self {
+			shared: Mutex::new(Buckets::new()),
+			counters: Counters::new(false),
+			locals: Mutex::new(Vec::new()),
+			builder,
+			policy: PoolPolicy::new()
+		}
+	}
+
+	/// Replace the pool's policy
+	#[must_use]
+	pub const fn policy(mut self, policy: PoolPolicy) -> Self {
+		self.policy = policy;
+		self
+	}
+
+	#[must_use]
+	pub const fn builder(&self) -> &FiberBuilder {
+		&self.builder
+	}
+
+	#[must_use]
+	pub const fn stack_mode(&self) -> StackMode {
+		self.builder.mode()
+	}
+
+	/// A snapshot of the pool's counters, including those of its local pools
+	#[must_use]
+	pub fn stats(&self) -> PoolStats {
+		let mut stats = PoolStats::default();
+
+		/* hold the lock, so that an exiting local pool isn't counted twice */
+		#[allow(clippy::unwrap_used)]
+		let locals = self.locals.lock().unwrap();
+
+		self.counters.accumulate(&mut stats);
+
+		for counters in locals.iter() {
+			counters.accumulate(&mut stats);
+		}
+
+		stats
+	}
+
+	/// The stack size of fibers created for `hint`
+	#[allow(clippy::expect_used)]
+	fn stack_size(&self, hint: StackHint) -> usize {
+		self.builder
+			.stack_hint(hint)
+			.resolved_stack_size()
+			.expect("Failed to get stack size")
+	}
+
+	/// Take a fiber from the shared pool
+	fn acquire(&self, stack_size: usize) -> Option<Fiber> {
+		/* we never panic with the lock */
+		#[allow(clippy::unwrap_used)]
+		self.shared.lock().unwrap().pop(stack_size)
+	}
+
+	/// Allocate a fiber for `hint`
+	#[allow(clippy::expect_used)]
+	fn create(&self, hint: StackHint, counters: &Counters) -> Fiber {
+		trace!(target: self, "++ Creating stack for worker");
+
+		counters.add(&counters.created, 1);
+
+		self.builder
+			.stack_hint(hint)
+			.build()
+			.expect("Failed to allocate stack for fiber")
+	}
+
+	/// Measure and release the memory of an exited fiber's stack
+	///
+	/// # Safety
+	/// fiber must be exited
+	unsafe fn retire(&self, fiber: &mut Fiber, counters: &Counters) {
+		if self.policy.paint_stacks {
+			/* Safety: guaranteed by caller */
+			counters.record_high_water(unsafe { fiber.stack_high_water() });
+
+			/* Safety: guaranteed by caller */
+			unsafe { fiber.zero_stack() };
+		} else {
+			/* Safety: guaranteed by caller */
+			unsafe { fiber.clear_stack() };
+		}
+	}
+
+	/// Keep a retired fiber in the shared pool if the policy allows
+	fn release(&self, fiber: Fiber) {
+		let active = self.counters.active.load(Ordering::Relaxed);
+
+		let dropped = {
 			/* we never panic with the lock */
 			#[allow(clippy::unwrap_used)]
-			let mut data = self.data.lock().unwrap();
+			let mut shared = self.shared.lock().unwrap();
 
-			data.active = data
-				.active
-				.checked_add(1)
-				.expect_nounwind("Fatal error: fiber count overflow");
-			data.pool.pop()
+			if self.policy.ideal(active) > shared.len {
+				shared.push(fiber)
+			} else {
+				Some(fiber)
+			}
 		};
 
-		match fiber {
-			Some(mut fiber) => {
+		/* free the stack after unlocking */
+		if dropped.is_some() {
+			trace!(target: self, "-- Dropping worker stack");
+
+			self.counters.sub(&self.counters.pooled, 1);
+			self.counters.add(&self.counters.dropped, 1);
+		} else {
+			trace!(target: self, "== Preserving worker stack");
+		}
+	}
+
+	/// # Panics
+	/// if creating a fiber fails
+	#[must_use]
+	pub fn new_fiber(&self, start: Start) -> Fiber {
+		self.new_fiber_with_hint(start, StackHint::Default)
+	}
+
+	/// Get a fiber with a stack of the size in `hint` from the shared pool,
+	/// or allocate one
+	///
+	/// # Panics
+	/// if creating a fiber fails
+	#[must_use]
+	pub fn new_fiber_with_hint(&self, start: Start, hint: StackHint) -> Fiber {
+		self.counters.start();
+
+		let mut fiber = match self.acquire(self.stack_size(hint)) {
+			Some(fiber) => {
 				trace!(target: self, "== Reusing stack for worker");
 
-				/* Safety: fiber was exited to us */
-				unsafe { fiber.set_start(start) };
+				self.counters.sub(&self.counters.pooled, 1);
+				self.counters.add(&self.counters.reused, 1);
 
 				fiber
 			}
 
-			None => {
-				trace!(target: self, "++ Creating stack for worker");
-
-				Fiber::new_with_start(start)
-			}
-		}
-	}
+			None => self.create(hint, &self.counters)
+		};
 
-	const fn calculate_ideal(count: u64) -> u64 {
-		const RATIO: u64 = 20;
+		/* Safety: fiber was exited to us, or never started */
+		unsafe { fiber.set_start(start) };
 
-		#[allow(clippy::arithmetic_side_effects)]
-		(count * RATIO / 100 + 16)
+		fiber
 	}
 
 	/// # Safety
 	/// fiber must be exited
 	///
 	/// This function never panics
+	pub unsafe fn exit_fiber(&self, mut fiber: Fiber) {
+		self.counters.sub(&self.counters.active, 1);
+
+		/* Safety: guaranteed by caller */
+		unsafe { self.retire(&mut fiber, &self.counters) };
+
+		self.counters.add(&self.counters.pooled, 1);
+
+		self.release(fiber);
+	}
+
+	/// Allocate `count` fibers with stacks of the size in `hint` ahead of
+	/// time, so that a burst of spawns doesn't have to
+	///
+	/// Prewarmed fibers may exceed what the policy would keep, until the next
+	/// [`trim`](Self::trim)
+	///
+	/// This function never panics
 	#[allow(clippy::missing_panics_doc)]
-	pub unsafe fn exit_fiber(&self, fiber: Fiber) {
+	pub fn prewarm(&self, count: usize, hint: StackHint) -> OsResult<()> {
+		let builder = self.builder.stack_hint(hint);
+
+		for _ in 0..count {
+			let fiber = builder.build()?;
+
+			/* we never panic with the lock */
+			#[allow(clippy::unwrap_used)]
+			if self.shared.lock().unwrap().push(fiber).is_some() {
+				return Err(OsError::NoMem);
+			}
+
+			self.counters.add(&self.counters.created, 1);
+			self.counters.add(&self.counters.pooled, 1);
+		}
+
+		Ok(())
+	}
+
+	/// Free fibers in the shared pool until at most [`PoolPolicy::idle`]
+	/// remain, starting with the largest stacks. Meant to be called when the
+	/// runtime goes idle. Returns the number of fibers freed
+	///
+	/// This function never panics
+	#[allow(clippy::missing_panics_doc)]
+	pub fn trim(&self) -> u64 {
 		/* we never panic with the lock */
 		#[allow(clippy::unwrap_used)]
-		let mut data = self.data.lock().unwrap();
+		let trimmed = self.shared.lock().unwrap().trim(self.policy.idle);
+		let count = trimmed.len() as u64;
 
-		data.active = data
-			.active
-			.checked_sub(1)
-			.expect_nounwind("Fatal error: fiber count overflow");
+		if count != 0 {
+			trace!(target: self, "-- Trimmed {} worker stacks", count);
 
-		let ideal = Self::calculate_ideal(data.active);
+			self.counters.sub(&self.counters.pooled, count);
+			self.counters.add(&self.counters.dropped, count);
+		}
 
-		if ideal > data.pool.len() as u64 && data.pool.try_reserve(1).is_ok() {
-			trace!(target: self, "== Preserving worker stack");
+		/* free the stacks after unlocking */
+		drop(trimmed);
 
-			data.pool.push(fiber);
-		} else {
-			trace!(target: self, "-- Dropping worker stack");
-		}
+		count
 	}
 }
 
@@ -98,3 +603,281 @@ impl Default for Pool {
 		Self::new()
 	}
 }
+
+struct ReturnNode {
+	fiber: Fiber,
+	next: MutPtr<ReturnNode>
+}
+
+/// A lock free stack of fibers returned to a [`LocalPool`] from other threads
+///
+/// Any thread may push, but only the owner takes, and it takes everything at
+/// once, so popping is not subject to ABA
+pub(super) struct ReturnStack {
+	head: AtomicMutPtr<ReturnNode>
+}
+
+impl ReturnStack {
+	/// Marks a stack whose owner is gone
+	const CLOSED: MutPtr<ReturnNode> = MutPtr::from_addr(1);
+
+	const fn new() -> Self {
+		Self { head: AtomicMutPtr::new(MutPtr::null()) }
+	}
+
+	/// Push `fiber`, or return it back if the owner is gone
+	fn push(&self, fiber: Fiber) -> Option<Fiber> {
+		let node: MutPtr<ReturnNode> =
+			Box::into_raw(Box::new(ReturnNode { fiber, next: MutPtr::null() })).into();
+		let mut head = self.head.load(Ordering::Relaxed);
+
+		loop {
+			if head == Self::CLOSED {
+				/* Safety: we just leaked this box */
+				let node = unsafe { Box::from_raw(node.as_mut_ptr()) };
+
+				return Some(node.fiber);
+			}
+
+			/* Safety: the node isn't shared until the exchange succeeds */
+			unsafe { ptr!(node=>next = head) };
+
+			match self
+				.head
+				.compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+			{
+				Ok(_) => return None,
+				Err(current) => head = current
+			}
+		}
+	}
+
+	/// Take all the fibers, replacing the head with `with`
+	fn take(&self, with: MutPtr<ReturnNode>) -> Vec<Fiber> {
+		let mut fibers = Vec::new();
+		let mut node = self.head.swap(with, Ordering::Acquire);
+
+		while !node.is_null() && node != Self::CLOSED {
+			/* Safety: nodes are leaked boxes, and we own them after the swap */
+			let boxed = unsafe { Box::from_raw(node.as_mut_ptr()) };
+
+			node = boxed.next;
+			fibers.push(boxed.fiber);
+		}
+
+		fibers
+	}
+
+	fn is_empty(&self) -> bool {
+		self.head.load(Ordering::Relaxed).is_null()
+	}
+}
+
+/// A per thread cache of fibers in front of a shared [`Pool`]
+///
+/// Allocating and exiting fibers on the owning thread never locks, unless
+/// the cache is empty or holds more than the pool's policy allows, in which
+/// case the shared pool is used. Fibers exiting on other threads are
+/// returned to the cache they came from without locking
+pub struct LocalPool {
+	pool: Ptr<Pool>,
+	cache: UnsafeCell<Buckets>,
+	active: Cell<u64>,
+	returns: Arc<ReturnStack>,
+	counters: Arc<Counters>
+}
+
+impl LocalPool {
+	/// # Safety
+	/// `pool` must be valid for as long as this local pool and any fiber it
+	/// hands out
+	#[must_use]
+	pub unsafe fn new(pool: Ptr<Pool>) -> Self {
+		let counters = Arc::new(Counters::new(true));
+
+		/* Safety: guaranteed by caller */
+		let shared = unsafe { pool.as_ref() };
+
+		/* we never panic with the lock */
+		#[allow(clippy::unwrap_used)]
+		shared.locals.lock().unwrap().push(counters.clone());
+
+		Self {
+			pool,
+			cache: UnsafeCell::new(Buckets::new()),
+			active: Cell::new(0),
+			returns: Arc::new(ReturnStack::new()),
+			counters
+		}
+	}
+
+	#[must_use]
+	pub const fn pool(&self) -> Ptr<Pool> {
+		self.pool
+	}
+
+	fn shared(&self) -> &Pool {
+		/* Safety: guaranteed by the contract of `new` */
+		unsafe { self.pool.as_ref() }
+	}
+
+	/// # Safety
+	/// caller must ensure an aliased &mut does not get created
+	#[allow(clippy::mut_from_ref)]
+	unsafe fn cache(&self) -> &mut Buckets {
+		/* Safety: guaranteed by caller */
+		unsafe { self.cache.as_mut() }
+	}
+
+	/// Move fibers returned from other threads into the cache
+	fn collect_returns(&self) {
+		if self.returns.is_empty() {
+			return;
+		}
+
+		for fiber in self.returns.take(MutPtr::null()) {
+			/* Safety: exclusive access */
+			if let Some(fiber) = unsafe { self.cache() }.push(fiber) {
+				self.shared().release(fiber);
+			}
+		}
+	}
+
+	/// # Panics
+	/// if creating a fiber fails
+	#[must_use]
+	pub fn new_fiber(&self, start: Start) -> Fiber {
+		self.new_fiber_with_hint(start, StackHint::Default)
+	}
+
+	/// Get a fiber with a stack of the size in `hint`, from the local cache
+	/// if possible
+	///
+	/// # Panics
+	/// if creating a fiber fails
+	#[must_use]
+	pub fn new_fiber_with_hint(&self, start: Start, hint: StackHint) -> Fiber {
+		let pool = self.shared();
+		let stack_size = pool.stack_size(hint);
+
+		pool.counters.start();
+
+		self.active.update(|active| active.saturating_add(1));
+		self.collect_returns();
+
+		/* Safety: exclusive access */
+		let cached = unsafe { self.cache() }.pop(stack_size);
+
+		let mut fiber = match cached.or_else(|| pool.acquire(stack_size)) {
+			Some(fiber) => {
+				trace!(target: self, "== Reusing stack for worker");
+
+				self.counters.sub(&self.counters.pooled, 1);
+				self.counters.add(&self.counters.reused, 1);
+
+				fiber
+			}
+
+			None => pool.create(hint, &self.counters)
+		};
+
+		if !fiber
+			.home
+			.as_ref()
+			.is_some_and(|home| Arc::ptr_eq(home, &self.returns))
+		{
+			fiber.home = Some(self.returns.clone());
+		}
+
+		/* Safety: fiber was exited to us, or never started */
+		unsafe { fiber.set_start(start) };
+
+		fiber
+	}
+
+	/// Return an exited fiber, to the cache it came from
+	///
+	/// # Safety
+	/// fiber must be exited
+	///
+	/// This function never panics
+	pub unsafe fn exit_fiber(&self, mut fiber: Fiber) {
+		let pool = self.shared();
+
+		pool.counters.sub(&pool.counters.active, 1);
+
+		self.active.update(|active| active.saturating_sub(1));
+
+		/* Safety: guaranteed by caller */
+		unsafe { pool.retire(&mut fiber, &self.counters) };
+
+		self.counters.add(&self.counters.pooled, 1);
+
+		let foreign = fiber
+			.home
+			.as_ref()
+			.filter(|home| !Arc::ptr_eq(home, &self.returns))
+			.cloned();
+
+		let fiber = match foreign {
+			Some(home) => {
+				trace!(target: self, "== Returning worker stack to its thread");
+
+				match home.push(fiber) {
+					Some(fiber) => fiber,
+					None => return
+				}
+			}
+
+			None => {
+				/* Safety: exclusive access */
+				let cache = unsafe { self.cache() };
+
+				if pool.policy.ideal(self.active.get()) <= cache.len {
+					fiber
+				} else {
+					match cache.push(fiber) {
+						Some(fiber) => fiber,
+						None => return
+					}
+				}
+			}
+		};
+
+		pool.release(fiber);
+	}
+
+	/// Move cached fibers beyond [`PoolPolicy::idle`] to the shared pool, then
+	/// [trim](Pool::trim) it. Returns the number of fibers freed
+	pub fn trim(&self) -> u64 {
+		let pool = self.shared();
+
+		self.collect_returns();
+
+		/* Safety: exclusive access */
+		for fiber in unsafe { self.cache() }.trim(pool.policy.idle) {
+			pool.release(fiber);
+		}
+
+		pool.trim()
+	}
+}
+
+impl Drop for LocalPool {
+	fn drop(&mut self) {
+		let pool = self.shared();
+		let returned = self.returns.take(ReturnStack::CLOSED);
+		let cached = self.cache.get_mut().trim(0);
+
+		for fiber in returned.into_iter().chain(cached) {
+			pool.release(fiber);
+		}
+
+		/* we never panic with the lock */
+		#[allow(clippy::unwrap_used)]
+		let mut locals = pool.locals.lock().unwrap();
+
+		self.counters.merge_into(&pool.counters);
+		locals.retain(|counters| !Arc::ptr_eq(counters, &self.counters));
+	}
+}
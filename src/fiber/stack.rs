@@ -0,0 +1,541 @@
+//! Growable fiber stacks
+//!
+//! A growable stack reserves address space for its maximum size up front, but
+//! only makes the top `initial` bytes accessible. When the fiber touches the
+//! reserved region below, the fault is handled on an alternate signal stack by
+//! making more of the reservation accessible, and the faulting instruction is
+//! retried. The lowest page of the reservation is a permanent guard, and
+//! hitting it aborts the process.
+//!
+//! The same handler reports overflows of any fiber's guard pages once
+//! [`install_overflow_handler`] is called.
+//!
+//! Faults that don't belong to the running fiber's stack are passed on to the
+//! handler that was installed before ours.
+//!
+//! Only accesses made by the fiber itself grow the stack. The kernel does not
+//! fault when a system call touches user memory, so a system call given a
+//! buffer in the part of the stack that isn't accessible yet fails with
+//! `EFAULT` instead. Fibers with growable stacks should keep large buffers
+//! passed to the kernel off the stack, or start with enough stack to hold
+//! them.
+
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::io::Write;
+use std::mem::size_of;
+use std::os::fd::BorrowedFd;
+use std::process::abort;
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use enumflags2::BitFlags;
+
+use super::*;
+use crate::impls::ResultExt;
+use crate::log::print_fatal;
+use crate::os::signal::*;
+
+/// The size of the alternate signal stack installed for threads running
+/// growable fibers, large enough to capture a backtrace on
+const SIGNAL_STACK_SIZE: usize = 0x40000;
+
+/// How fiber stacks are allocated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackMode {
+	/// The full stack size is committed up front
+	Fixed,
+
+	/// Start with `initial` bytes of stack, growing on demand up to `max`
+	/// bytes. Both are rounded up to the page size
+	///
+	/// When used with [`FiberBuilder::stack_mode`], `max` replaces the stack
+	/// size
+	Growable { initial: usize, max: usize }
+}
+
+impl Default for StackMode {
+	fn default() -> Self {
+		Self::Fixed
+	}
+}
+
+enum Fault {
+	Grown,
+	Overflow,
+	Unrelated
+}
+
+/// The bounds of a growable stack, shared with the fault handler
+pub(super) struct Growth {
+	/// The start of the reservation
+	base: usize,
+
+	/// The lowest address that can be made accessible. Below this is the
+	/// permanent guard
+	limit: usize,
+
+	/// The lowest accessible address
+	committed: AtomicUsize,
+
+	/// The lowest accessible address of a fresh stack
+	initial: usize,
+
+	/// The end of the reservation
+	top: usize,
+
+	/// Cached, as querying it isn't async signal safe
+	page_size: usize
+}
+
+impl Growth {
+	/// Manage the reserved, inaccessible `stack`, whose lowest `guard` bytes
+	/// are a permanent guard. The top `initial` bytes are made accessible
+	///
+	/// `guard` and `initial` must be multiples of the page size
+	pub(super) fn new(stack: &Map<'static>, guard: usize, initial: usize) -> OsResult<Self> {
+		let base = stack.as_ptr().addr();
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let top = base + stack.len();
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let growth = Self {
+			base,
+			limit: base + guard,
+			committed: AtomicUsize::new(top),
+			initial: top - initial.min(stack.len() - guard),
+			top,
+			page_size: page_size()
+		};
+
+		/* Safety: the range is part of the reservation, which nothing uses yet */
+		unsafe { growth.protect(growth.initial, top, Protection::Read | Protection::Write) }?;
+
+		growth.committed.store(growth.initial, Ordering::Relaxed);
+
+		install_handler();
+
+		Ok(growth)
+	}
+
+	/// # Safety
+	/// the range must be part of the reservation and not in use
+	unsafe fn protect(&self, start: usize, end: usize, prot: BitFlags<Protection>) -> OsResult<()> {
+		#[allow(clippy::arithmetic_side_effects)]
+		let section = RawBuf::from_parts(Ptr::from_addr(start), end - start);
+
+		/* Safety: guaranteed by caller */
+		unsafe { mprotect(section, prot) }
+	}
+
+	fn grow(&self, addr: usize) -> Fault {
+		let committed = self.committed.load(Ordering::Relaxed);
+
+		if addr < self.base || addr >= committed {
+			return Fault::Unrelated;
+		}
+
+		if addr < self.limit {
+			return Fault::Overflow;
+		}
+
+		/* grow by at least double the current size, to limit the number of faults */
+		#[allow(clippy::arithmetic_side_effects)]
+		let doubled = committed.saturating_sub(self.top - committed);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let start = (addr - addr % self.page_size).min(doubled).max(self.limit);
+
+		/* Safety: the range is reserved and inaccessible, so it can't be in use */
+		if unsafe { self.protect(start, committed, Protection::Read | Protection::Write) }.is_err()
+		{
+			return Fault::Overflow;
+		}
+
+		self.committed.store(start, Ordering::Relaxed);
+
+		Fault::Grown
+	}
+
+	/// The lowest accessible address
+	pub(super) fn committed(&self) -> usize {
+		self.committed.load(Ordering::Relaxed)
+	}
+
+	/// Shrink the stack back to its initial size
+	///
+	/// # Safety
+	/// The fiber must not be running
+	pub(super) unsafe fn reset(&self) {
+		let committed = self.committed.load(Ordering::Relaxed);
+
+		if committed >= self.initial {
+			return;
+		}
+
+		/* Safety: the fiber isn't running. if this fails, the stack simply stays
+		 * larger */
+		if unsafe { self.protect(committed, self.initial, BitFlags::default()) }.is_ok() {
+			self.committed.store(self.initial, Ordering::Relaxed);
+		}
+	}
+}
+
+/// The bounds of a fiber's stack, shared with the fault handler
+///
+/// Kept on the heap, as the fiber itself may be moved while it is running
+pub(super) struct Bounds {
+	/// The start of the reservation, where the guard pages begin
+	base: usize,
+
+	/// The end of the guard pages
+	limit: usize,
+
+	/// The end of the reservation
+	top: usize,
+
+	growth: Option<Growth>,
+
+	/// The id of the task running on the fiber, or zero if unknown
+	owner: Cell<u64>
+}
+
+impl Bounds {
+	/// The bounds of `stack`, whose lowest `guard` bytes are guard pages
+	#[allow(clippy::arithmetic_side_effects)]
+	pub(super) fn new(stack: &Map<'static>, guard: usize, growth: Option<Growth>) -> Self {
+		let base = stack.as_ptr().addr();
+
+		Self {
+			base,
+			limit: base + guard,
+			top: base + stack.len(),
+			growth,
+			owner: Cell::new(0)
+		}
+	}
+
+	pub(super) const fn growth(&self) -> Option<&Growth> {
+		self.growth.as_ref()
+	}
+
+	/// Returns `true` if `addr` is in the guard pages below the stack
+	fn in_guard(&self, addr: usize) -> bool {
+		addr >= self.base && addr < self.limit
+	}
+}
+
+thread_local! {
+	/* const initialized without a destructor, so it's safe to access from a
+	 * signal handler */
+	static CURRENT: Cell<Ptr<Bounds>> = const { Cell::new(Ptr::null()) };
+
+	static SIGNAL_STACK: SignalStackGuard = SignalStackGuard::new();
+}
+
+/// An alternate signal stack for the current thread
+struct SignalStackGuard {
+	/// Our stack, or empty if the thread's own stack was large enough
+	stack: Map<'static>,
+
+	/// The stack that was installed before ours, restored on drop
+	previous: SignalStack
+}
+
+impl SignalStackGuard {
+	fn new() -> Self {
+		let mut previous = SignalStack::default();
+
+		/* Safety: we don't set a new stack */
+		unsafe { sigaltstack(None, Some(&mut previous)) }
+			.expect_nounwind("Failed to get the signal stack");
+
+		if previous.flags & SignalStackFlag::Disable as u32 == 0 &&
+			previous.size >= SIGNAL_STACK_SIZE
+		{
+			/* large enough to capture a backtrace on, so keep it */
+			return Self { stack: Map::new(), previous };
+		}
+
+		let stack = Builder::new(Type::Private, SIGNAL_STACK_SIZE)
+			.protect(Protection::Read | Protection::Write)
+			.flag(Flag::Anonymous | Flag::Stack | Flag::NoReserve)
+			.map()
+			.expect_nounwind("Failed to allocate signal stack");
+
+		let new = SignalStack { sp: stack.as_ptr(), flags: 0, size: stack.len() };
+
+		/* replaces the runtime's stack, if any, which is too small to capture a
+		 * backtrace on. it is restored in drop
+		 *
+		 * Safety: the stack lives until we replace it in drop
+		 */
+		unsafe { sigaltstack(Some(&new), None) }.expect_nounwind("Failed to set the signal stack");
+
+		Self { stack, previous }
+	}
+}
+
+impl Drop for SignalStackGuard {
+	fn drop(&mut self) {
+		if self.stack.as_ptr().is_null() {
+			return;
+		}
+
+		let mut current = SignalStack::default();
+
+		/* Safety: we don't set a new stack */
+		if unsafe { sigaltstack(None, Some(&mut current)) }.is_err() ||
+			current.sp != self.stack.as_ptr()
+		{
+			/* someone else replaced it */
+			return;
+		}
+
+		/* Safety: the previous stack was installed before ours, and its owner
+		 * expects it to still be in place. ours is replaced before it is
+		 * unmapped */
+		unsafe { sigaltstack(Some(&self.previous), None) }
+			.expect_nounwind("Failed to restore the signal stack");
+	}
+}
+
+/// Whether overflows are reported with [`print_fatal`]
+static REPORT_OVERFLOW: AtomicBool = AtomicBool::new(false);
+
+/// Set the stack bounds of the fiber about to run on this thread, or `None`
+/// if it has no stack of its own
+///
+/// # Safety
+/// `bounds` must be valid for as long as the fiber is running
+pub(super) unsafe fn set_current(bounds: Option<&Bounds>) {
+	let growable = bounds.is_some_and(|bounds| bounds.growth.is_some());
+
+	if growable || REPORT_OVERFLOW.load(Ordering::Relaxed) {
+		SIGNAL_STACK.with(|_| ());
+	}
+
+	CURRENT.with(|current| current.set(bounds.map_or_else(Ptr::null, Ptr::from)));
+}
+
+/// The handler that was installed before ours
+static PREVIOUS_HANDLER: OnceLock<SigAction> = OnceLock::new();
+
+fn install_handler() {
+	PREVIOUS_HANDLER.get_or_init(|| {
+		let mut action = SigAction::default();
+		let mut previous = SigAction::default();
+
+		action.handler.action = Some(handle_fault);
+		action.flags = (SignalFlags::SigInfo | SignalFlags::OnStack).bits();
+
+		sig_action(
+			Signal::SegmentationViolation as i32,
+			Some(&action),
+			Some(&mut previous)
+		)
+		.expect_nounwind("Failed to install the stack fault handler");
+
+		previous
+	});
+}
+
+/// Report fiber stack overflows before aborting
+///
+/// When a fiber touches the guard pages below its stack, the faulting address,
+/// the thread and the [owner](Fiber::set_owner) of the fiber, its stack bounds
+/// and a backtrace are logged with [`print_fatal`], instead of the process
+/// dying to a bare `SIGSEGV`
+///
+/// The report is written from a signal handler running on an alternate stack,
+/// which is installed for each thread the next time it switches fibers.
+/// Capturing a backtrace there is not async signal safe, so this is a best
+/// effort diagnostic meant for debugging
+///
+/// Faults outside of fiber guard pages are passed on to the handler that was
+/// previously installed
+pub fn install_overflow_handler() {
+	REPORT_OVERFLOW.store(true, Ordering::Relaxed);
+
+	install_handler();
+}
+
+#[cold]
+#[allow(clippy::arithmetic_side_effects)]
+fn overflow(bounds: &Bounds, addr: usize) -> ! {
+	let Bounds { base, limit, top, .. } = *bounds;
+	let owner = bounds.owner.get();
+
+	if REPORT_OVERFLOW.load(Ordering::Relaxed) {
+		print_fatal(format_args!(
+			"Fiber overflowed its stack, accessing {:#x}",
+			addr
+		));
+
+		if owner != 0 {
+			print_fatal(format_args!(">> Task: #{}", owner));
+		}
+
+		print_fatal(format_args!(
+			">> Stack: {:#x}..{:#x} ({} bytes), guard: {:#x}..{:#x}",
+			limit,
+			top,
+			top - limit,
+			base,
+			limit
+		));
+
+		print_fatal(format_args!(
+			"\nBack trace:\n{}",
+			Backtrace::force_capture()
+		));
+	} else {
+		let mut message = [0u8; 128];
+		let mut buf = &mut message[..];
+
+		let _ = write!(buf, "Fatal error: fiber stack overflow");
+
+		if owner != 0 {
+			let _ = write!(buf, " in task #{}", owner);
+		}
+
+		let _ = writeln!(buf, " (maximum size is {} bytes)", top - limit);
+
+		let len = message.len().saturating_sub(buf.len());
+
+		/* Safety: stderr is never closed by us */
+		let stderr = unsafe { BorrowedFd::borrow_raw(2) };
+		let _ = write(stderr, (&message[..len]).into());
+	}
+
+	abort();
+}
+
+unsafe extern "C" fn handle_fault(signal: i32, info: MutPtr<SigInfo>, context: MutPtr<()>) {
+	/* Safety: the kernel passes a valid siginfo for a fault */
+	let addr = unsafe { info.as_ref().fields.fault.addr.addr() };
+	let bounds = CURRENT.with(Cell::get);
+
+	if !bounds.is_null() {
+		/* Safety: the running fiber's bounds are valid */
+		let bounds = unsafe { bounds.as_ref() };
+		let fault = bounds
+			.growth
+			.as_ref()
+			.map_or(Fault::Unrelated, |growth| growth.grow(addr));
+
+		match fault {
+			Fault::Grown => return,
+			Fault::Overflow => overflow(bounds, addr),
+			Fault::Unrelated if bounds.in_guard(addr) => overflow(bounds, addr),
+			Fault::Unrelated => ()
+		}
+	}
+
+	/* Safety: forwarding the arguments from the kernel */
+	unsafe { chain_fault(signal, info, context) };
+}
+
+/// Pass a fault that isn't ours to the handler that was installed before
+/// ours, leaving ours installed
+///
+/// # Safety
+/// Must only be called from the fault handler, with its arguments
+unsafe fn chain_fault(signal: i32, info: MutPtr<SigInfo>, context: MutPtr<()>) {
+	let previous = PREVIOUS_HANDLER.get().copied().unwrap_or_default();
+
+	/* Safety: every variant is a pointer sized value, and null is `None` */
+	let handler = unsafe { previous.handler.handler };
+
+	match handler {
+		/* a fault can't be ignored. the kernel kills the process when the
+		 * access is retried, as with the default action */
+		Some(handler) if handler as usize != SigHandlers::Ignore as usize => {
+			if previous.flags & SignalFlags::SigInfo as u32 != 0 {
+				/* Safety: the handler takes siginfo, as its flags say */
+				unsafe { (previous.handler.action.unwrap_unchecked())(signal, info, context) };
+			} else {
+				/* Safety: calling the handler as the kernel would */
+				unsafe { handler(signal) };
+			}
+		}
+
+		/* the default action terminates the process once the access is
+		 * retried, so our handler isn't needed anymore */
+		_ => {
+			let _ = sig_action(signal, Some(&SigAction::default()), None);
+		}
+	}
+}
+
+impl Fiber {
+	pub(super) fn growth(&self) -> Option<&Growth> {
+		self.bounds.as_deref().and_then(Bounds::growth)
+	}
+
+	/// Label the fiber with the id of the task running on it, which is
+	/// reported if the fiber overflows its stack. Zero clears the label
+	pub fn set_owner(&self, owner: u64) {
+		if let Some(bounds) = &self.bounds {
+			bounds.owner.set(owner);
+		}
+	}
+
+	/// The deepest the stack has been used since it was last zeroed, in bytes
+	///
+	/// Stacks are zero when allocated and after [`Fiber::zero_stack`], so the
+	/// lowest non zero word marks the high water. Stack frames that were
+	/// reserved but never written to are not counted
+	///
+	/// This reads the entire stack, and is meant for measurement rather than
+	/// production use
+	///
+	/// # Safety
+	/// The fiber must not be running
+	#[must_use]
+	#[allow(clippy::arithmetic_side_effects, clippy::cast_ptr_alignment)]
+	pub unsafe fn stack_high_water(&self) -> usize {
+		let base = self.stack.as_ptr().addr();
+		let top = base + self.stack.len();
+		let start = match self.growth() {
+			Some(growth) => growth.committed(),
+			None => base + self.guard
+		};
+
+		if start >= top {
+			return 0;
+		}
+
+		/* Safety: the range is accessible and page aligned, and the fiber isn't
+		 * running */
+		let words = unsafe {
+			slice::from_raw_parts(
+				Ptr::<usize>::from_addr(start).as_ptr(),
+				(top - start) / size_of::<usize>()
+			)
+		};
+
+		words
+			.iter()
+			.position(|word| *word != 0)
+			.map_or(0, |index| top - start - index * size_of::<usize>())
+	}
+
+	/// Release the memory backing the stack, like [`Fiber::clear_stack`], but
+	/// guarantee that the stack reads as zero afterwards, for measuring its
+	/// [high water](Fiber::stack_high_water)
+	///
+	/// # Safety
+	/// The fiber must not be running
+	pub unsafe fn zero_stack(&mut self) {
+		/* Safety: fiber isn't running */
+		let _ = unsafe { self.stack.advise(Advice::DontNeed) };
+
+		if let Some(growth) = self.growth() {
+			/* Safety: fiber isn't running */
+			unsafe { growth.reset() };
+		}
+	}
+}
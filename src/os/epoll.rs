@@ -111,6 +111,11 @@ impl EventPoll {
 		epoll_create1(flags).map(Self)
 	}
 
+	#[must_use]
+	pub fn fd(&self) -> BorrowedFd<'_> {
+		self.0.as_fd()
+	}
+
 	pub fn ctl(&self, op: ControlOp, fd: BorrowedFd<'_>, event: &mut Event) -> OsResult<()> {
 		epoll_ctl(self.0.as_fd(), op, fd, event)
 	}
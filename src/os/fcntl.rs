@@ -102,3 +102,114 @@ define_enum! {
 impl AtFlag {
 	pub const RemoveDir: Self = Self::EAccess;
 }
+
+define_enum! {
+	#[repr(u32)]
+	pub enum FcntlCmd {
+		DupFd        = 0,
+		GetFd        = 1,
+		SetFd        = 2,
+		GetFl        = 3,
+		SetFl        = 4,
+		GetLk        = 5,
+		SetLk        = 6,
+		SetLkWait    = 7,
+		SetOwn       = 8,
+		GetOwn       = 9,
+		SetSig       = 10,
+		GetSig       = 11,
+		SetLease     = 1024,
+		GetLease     = 1025,
+		Notify       = 1026,
+		DupFdCloExec = 1030,
+		SetPipeSize  = 1031,
+		GetPipeSize  = 1032,
+		AddSeals     = 1033,
+		GetSeals     = 1034
+	}
+}
+
+/// The close-on-exec flag for [`FcntlCmd::GetFd`] and [`FcntlCmd::SetFd`]
+pub const FD_CLOEXEC: usize = 1;
+
+/// # Safety
+/// `arg` must be valid for `cmd`
+#[syscall_define(Fcntl)]
+pub unsafe fn fcntl(fd: BorrowedFd<'_>, cmd: FcntlCmd, arg: usize) -> OsResult<i32>;
+
+define_enum! {
+	#[bitflags]
+	#[repr(u32)]
+	pub enum Seal {
+		/// Prevent further seals from being added
+		Seal        = 1 << 0,
+
+		/// Prevent the file from shrinking
+		Shrink      = 1 << 1,
+
+		/// Prevent the file from growing
+		Grow        = 1 << 2,
+
+		/// Prevent writes, including through shared writable mappings.
+		/// Fails with [`OsError::Busy`] if such a mapping exists
+		///
+		/// [`OsError::Busy`]: super::error::OsError::Busy
+		Write       = 1 << 3,
+
+		/// Prevent future writes, while allowing existing writable mappings
+		FutureWrite = 1 << 4,
+
+		/// Prevent changes to the execute permission bits
+		Exec        = 1 << 5
+	}
+}
+
+/// Add `seals` to the memfd `fd`
+///
+/// Fails with [`OsError::Perm`] if [`Seal::Seal`] is set
+///
+/// [`OsError::Perm`]: super::error::OsError::Perm
+pub fn add_seals(fd: BorrowedFd<'_>, seals: BitFlags<Seal>) -> OsResult<()> {
+	/* Safety: the argument is an integer */
+	unsafe { fcntl(fd, FcntlCmd::AddSeals, seals.bits() as usize) }?;
+
+	Ok(())
+}
+
+/// Returns the seals on `fd`
+pub fn get_seals(fd: BorrowedFd<'_>) -> OsResult<BitFlags<Seal>> {
+	/* Safety: the command takes no argument */
+	let seals = unsafe { fcntl(fd, FcntlCmd::GetSeals, 0) }?;
+
+	#[allow(clippy::cast_sign_loss)]
+	Ok(BitFlags::from_bits_truncate(seals as u32))
+}
+
+/// Returns the file status flags of `fd`, such as [`OpenFlag::NonBlock`]
+pub fn get_status_flags(fd: BorrowedFd<'_>) -> OsResult<BitFlags<OpenFlag>> {
+	/* Safety: the command takes no argument */
+	let flags = unsafe { fcntl(fd, FcntlCmd::GetFl, 0) }?;
+
+	#[allow(clippy::cast_sign_loss)]
+	Ok(BitFlags::from_bits_truncate(flags as u32))
+}
+
+/// Sets the file status flags of `fd`. Only the append, async, direct,
+/// noatime and nonblock flags can be changed
+pub fn set_status_flags(fd: BorrowedFd<'_>, flags: BitFlags<OpenFlag>) -> OsResult<()> {
+	/* Safety: the argument is an integer */
+	unsafe { fcntl(fd, FcntlCmd::SetFl, flags.bits() as usize) }?;
+
+	Ok(())
+}
+
+/// Sets or clears [`OpenFlag::NonBlock`] on `fd`
+///
+/// The flag belongs to the open file description, so it is shared by every
+/// duplicate of `fd`, including those inherited by child processes
+pub fn set_nonblocking(fd: BorrowedFd<'_>, nonblocking: bool) -> OsResult<()> {
+	let mut flags = get_status_flags(fd)?;
+
+	flags.set(OpenFlag::NonBlock, nonblocking);
+	set_status_flags(fd, flags)
+}
@@ -36,6 +36,50 @@ pub unsafe fn futex(
 	value3: u32
 ) -> OsResult<u64>;
 
+/// Wait until `word` is woken, if it still contains `expected`
+///
+/// Returns [`OsError::Again`] if the value did not match, and
+/// [`OsError::TimedOut`] if `timeout` elapsed. If `private` is `true`, the
+/// futex cannot be shared with other processes
+pub fn futex_wait(
+	word: &AtomicU32, expected: u32, timeout: Option<&TimeSpec>, private: bool
+) -> OsResult<()> {
+	let mut op = FutexOp::Wait as i32;
+
+	if private {
+		op |= FutexOp::PrivateFlag;
+	}
+
+	/* Safety: the futex is borrowed for the duration of the call */
+	unsafe {
+		futex(
+			word.as_ptr().into(),
+			op,
+			expected,
+			timeout,
+			MutPtr::null(),
+			0
+		)
+	}?;
+
+	Ok(())
+}
+
+/// Wake up to `count` waiters on `word`, returning the number woken
+pub fn futex_wake(word: &AtomicU32, count: u32, private: bool) -> OsResult<u32> {
+	let mut op = FutexOp::Wake as i32;
+
+	if private {
+		op |= FutexOp::PrivateFlag;
+	}
+
+	/* Safety: waking never accesses the futex word */
+	let woken = unsafe { futex(word.as_ptr().into(), op, count, None, MutPtr::null(), 0) }?;
+
+	#[allow(clippy::cast_possible_truncation)]
+	Ok(woken as u32)
+}
+
 #[repr(u32)]
 enum State {
 	Parked = 0,
@@ -0,0 +1,159 @@
+use std::ptr::read_unaligned;
+
+use super::fcntl::OpenFlag;
+use super::*;
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum InitFlag {
+		NonBlock    = OpenFlag::NonBlock as u32,
+		CloseOnExec = OpenFlag::CloseOnExec as u32
+	}
+}
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum WatchFlag {
+		/// File was accessed
+		Access        = 1 << 0,
+
+		/// File was modified
+		Modify        = 1 << 1,
+
+		/// Metadata changed
+		Attrib        = 1 << 2,
+
+		/// Writable file was closed
+		CloseWrite    = 1 << 3,
+
+		/// Unwritable file was closed
+		CloseNoWrite  = 1 << 4,
+
+		/// File was opened
+		Open          = 1 << 5,
+
+		/// File was moved out of the watched directory
+		MovedFrom     = 1 << 6,
+
+		/// File was moved into the watched directory
+		MovedTo       = 1 << 7,
+
+		/// File was created in the watched directory
+		Create        = 1 << 8,
+
+		/// File was deleted from the watched directory
+		Delete        = 1 << 9,
+
+		/// The watched path itself was deleted
+		DeleteSelf    = 1 << 10,
+
+		/// The watched path itself was moved
+		MoveSelf      = 1 << 11,
+
+		/// The filesystem containing the watched path was unmounted
+		Unmount       = 1 << 13,
+
+		/// The event queue overflowed
+		QueueOverflow = 1 << 14,
+
+		/// The watch was removed
+		Ignored       = 1 << 15,
+
+		/// Only watch the path if it is a directory
+		OnlyDir       = 1 << 24,
+
+		/// Do not follow symlinks
+		DontFollow    = 1 << 25,
+
+		/// Do not generate events for children after they are unlinked
+		ExclUnlink    = 1 << 26,
+
+		/// Fail if a watch for the path already exists
+		MaskCreate    = 1 << 28,
+
+		/// Add to the mask of an existing watch instead of replacing it
+		MaskAdd       = 1 << 29,
+
+		/// The subject of the event is a directory
+		IsDir         = 1 << 30,
+
+		/// Remove the watch after one event
+		OneShot       = 1 << 31
+	}
+}
+
+define_struct! {
+	pub struct EventHeader {
+		pub wd: i32,
+		pub mask: u32,
+		pub cookie: u32,
+		pub len: u32
+	}
+}
+
+/// The size of a buffer guaranteed to fit at least one event
+pub const MIN_EVENT_BUFFER_SIZE: usize = size_of::<EventHeader>() + 256;
+
+#[syscall_define(InotifyInit1)]
+pub fn inotify_init1(flags: BitFlags<InitFlag>) -> OsResult<OwnedFd>;
+
+#[syscall_define(InotifyAddWatch)]
+pub fn inotify_add_watch(
+	fd: BorrowedFd<'_>, path: &CStr, mask: BitFlags<WatchFlag>
+) -> OsResult<i32>;
+
+#[syscall_define(InotifyRmWatch)]
+pub fn inotify_rm_watch(fd: BorrowedFd<'_>, wd: i32) -> OsResult<()>;
+
+/// An event read from an inotify descriptor
+#[derive(Clone, Copy, Debug)]
+pub struct Event<'a> {
+	pub wd: i32,
+	pub mask: BitFlags<WatchFlag>,
+	pub cookie: u32,
+	pub name: Option<&'a CStr>
+}
+
+/// An iterator over the events in a buffer filled by `read`
+pub struct Events<'a> {
+	buf: &'a [u8]
+}
+
+impl<'a> Events<'a> {
+	#[must_use]
+	pub const fn new(buf: &'a [u8]) -> Self {
+		Self { buf }
+	}
+}
+
+impl<'a> Iterator for Events<'a> {
+	type Item = Event<'a>;
+
+	fn next(&mut self) -> Option<Event<'a>> {
+		let header_size = size_of::<EventHeader>();
+
+		if self.buf.len() < header_size {
+			return None;
+		}
+
+		/* Safety: the buffer is large enough to hold a header */
+		let header: EventHeader = unsafe { read_unaligned(self.buf.as_ptr().cast()) };
+		let len = header.len as usize;
+		let (name, rest) = self.buf[header_size..].split_at(len.min(self.buf.len() - header_size));
+
+		self.buf = rest;
+
+		let name = CStr::from_bytes_until_nul(name)
+			.ok()
+			.filter(|name| !name.is_empty());
+
+		Some(Event {
+			wd: header.wd,
+			mask: BitFlags::from_bits_truncate(header.mask),
+			cookie: header.cookie,
+			name
+		})
+	}
+}
@@ -703,6 +703,139 @@ pub fn io_uring_register_sync_cancel(
 	Ok(())
 }
 
+/// A builder for setting up an io_uring instance
+///
+/// Configures the [`Parameters`] passed to [`io_uring_setup`], including
+/// submission queue polling, the CPU the polling thread is pinned to, and
+/// sharing of the async worker backend between rings
+pub struct Builder<'fd> {
+	entries: u32,
+	params: Parameters,
+	flags: BitFlags<SetupFlag>,
+	phantom: PhantomData<BorrowedFd<'fd>>
+}
+
+impl<'fd> Builder<'fd> {
+	#[must_use]
+	pub fn new(entries: u32) -> Self {
+		Self {
+			entries,
+			params: Parameters::default(),
+			flags: BitFlags::default(),
+			phantom: PhantomData
+		}
+	}
+
+	#[must_use]
+	pub fn flag<F>(mut self, flags: F) -> Self
+	where
+		F: Into<BitFlags<SetupFlag>>
+	{
+		self.flags |= flags.into();
+		self
+	}
+
+	/// Sets the size of the completion ring, instead of the default of twice
+	/// the number of submission entries
+	#[must_use]
+	pub fn completion_entries(mut self, entries: u32) -> Self {
+		self.params.cq_entries = entries;
+		self.flag(SetupFlag::CompletionRingSize)
+	}
+
+	/// Clamp the number of entries to the maximum instead of failing
+	#[must_use]
+	pub fn clamp(self) -> Self {
+		self.flag(SetupFlag::Clamp)
+	}
+
+	/// Create a kernel thread to poll the submission queue. The thread goes to
+	/// sleep after `idle` passes without any submissions
+	#[must_use]
+	pub fn submission_queue_polling(mut self, idle: Duration) -> Self {
+		self.params.sq_thread_idle = idle.as_millis().try_into().unwrap_or(u32::MAX);
+		self.flag(SetupFlag::SubmissionQueuePolling)
+	}
+
+	/// Pin the submission queue polling thread to `cpu`
+	///
+	/// Only valid with [`Builder::submission_queue_polling`]
+	#[must_use]
+	pub fn submission_queue_cpu(mut self, cpu: u32) -> Self {
+		self.params.sq_thread_cpu = cpu;
+		self.flag(SetupFlag::SubmissionQueueAffinity)
+	}
+
+	/// Share the async worker backend (and the polling thread, if any) of the
+	/// ring `fd` instead of creating a new one
+	#[must_use]
+	#[allow(clippy::cast_sign_loss)]
+	pub fn attach_wq(mut self, fd: BorrowedFd<'fd>) -> Self {
+		self.params.wq_fd = fd.as_raw_fd() as u32;
+		self.flag(SetupFlag::AttachWq)
+	}
+
+	/// Do not interrupt the submitting thread with an IPI to run task work
+	/// when a completion is posted
+	#[must_use]
+	pub fn coop_taskrun(self) -> Self {
+		self.flag(SetupFlag::CoopTaskrun)
+	}
+
+	/// Set a flag in the submission ring when task work is pending, so that
+	/// the ring can be entered to run it
+	///
+	/// Only valid with [`Builder::coop_taskrun`] or [`Builder::defer_taskrun`]
+	#[must_use]
+	pub fn taskrun_flag(self) -> Self {
+		self.flag(SetupFlag::TaskrunFlag)
+	}
+
+	/// Hint that only one thread will submit requests
+	#[must_use]
+	pub fn single_issuer(self) -> Self {
+		self.flag(SetupFlag::SingleIssuer)
+	}
+
+	/// Defer task work until the ring is entered with
+	/// [`EnterFlag::GetEvents`]. Implies [`Builder::single_issuer`]
+	#[must_use]
+	pub fn defer_taskrun(self) -> Self {
+		self.flag(SetupFlag::DeferTaskrun | SetupFlag::SingleIssuer)
+	}
+
+	/// Start the ring disabled. It must be enabled with
+	/// [`RegisterOp::RegisterEnableRings`] before use
+	#[must_use]
+	pub fn disabled(self) -> Self {
+		self.flag(SetupFlag::RingDisabled)
+	}
+
+	#[must_use]
+	pub const fn flags(&self) -> BitFlags<SetupFlag> {
+		self.flags
+	}
+
+	/// Returns the flags requested by this builder that are not supported by
+	/// the kernel, as described by `features`
+	#[must_use]
+	pub fn unsupported(&self, features: &IoRingFeatures) -> BitFlags<SetupFlag> {
+		self.flags & !features.setup_flags
+	}
+
+	/// Create the ring, returning its fd and the parameters filled in by the
+	/// kernel
+	pub fn setup(self) -> OsResult<(OwnedFd, Parameters)> {
+		let mut params = self.params;
+
+		params.set_flags(self.flags);
+
+		let fd = io_uring_setup(self.entries, &mut params)?;
+
+		Ok((fd, params))
+	}
+}
+
 define_struct! {
 	pub struct IoRingFeatures {
 		pub min_ver: u32,
@@ -1,3 +1,8 @@
+use std::mem::ManuallyDrop;
+
+use super::fcntl::{add_seals, get_seals, Seal};
+use super::stat::{statx_fd, Statx, StatxMask};
+use super::unistd::{ftruncate, get_system_configuration, SystemConfiguration};
 use super::*;
 use crate::impls::ResultExt;
 
@@ -100,6 +105,18 @@ define_enum! {
 	}
 }
 
+define_enum! {
+	#[bitflags]
+	#[repr(u32)]
+	pub enum MemfdFlag {
+		CloseOnExec  = 1 << 0,
+		AllowSealing = 1 << 1,
+		HugeTLB      = 1 << 2,
+		NoExecSeal   = 1 << 3,
+		Exec         = 1 << 4
+	}
+}
+
 pub struct Map<'mem> {
 	addr: MutPtr<()>,
 	length: usize,
@@ -261,6 +278,11 @@ pub unsafe fn mremap(
 	new_address: Ptr<()>
 ) -> OsResult<MutPtr<()>>;
 
+/// Create an anonymous file living in memory. `name` is only used for
+/// debugging, and does not need to be unique
+#[syscall_define(MemfdCreate)]
+pub fn memfd_create(name: &CStr, flags: BitFlags<MemfdFlag>) -> OsResult<OwnedFd>;
+
 impl Default for Map<'static> {
 	fn default() -> Self {
 		Self::new()
@@ -287,6 +309,20 @@ impl<'mem> Map<'mem> {
 		Ok(Map { addr, length, phantom: PhantomData })
 	}
 
+	/// Map `length` bytes of the file `fd` starting at `offset`, sharing
+	/// changes with other mappings of the file and with the file itself
+	///
+	/// Use [`Map::flush`] to write changes back to the underlying storage
+	pub fn map_shared(
+		fd: BorrowedFd<'_>, offset: isize, length: usize, prot: BitFlags<Protection>
+	) -> OsResult<Map<'static>> {
+		Builder::new(Type::Shared, length)
+			.protect(prot)
+			.fd(fd)
+			.offset(offset)
+			.map()
+	}
+
 	#[must_use]
 	pub const fn as_ptr(&self) -> MutPtr<()> {
 		self.addr
@@ -317,6 +353,51 @@ impl<'mem> Map<'mem> {
 		unsafe { msync(self.section(), flags) }
 	}
 
+	/// Write changes made through a shared file mapping back to the file,
+	/// waiting until the write completes
+	pub fn flush(&self) -> OsResult<()> {
+		/* Safety: we own the mapping */
+		unsafe { self.sync(SyncFlag::Sync.into()) }
+	}
+
+	/// Schedule changes made through a shared file mapping to be written back
+	/// to the file, without waiting
+	pub fn flush_async(&self) -> OsResult<()> {
+		/* Safety: we own the mapping */
+		unsafe { self.sync(SyncFlag::Async.into()) }
+	}
+
+	/// Write changes to `length` bytes starting at `offset` back to the file,
+	/// waiting until the write completes. `offset` is rounded down to a page
+	/// boundary
+	///
+	/// # Panics
+	/// If the range is out of bounds
+	pub fn flush_range(&self, offset: usize, length: usize) -> OsResult<()> {
+		assert!(
+			offset
+				.checked_add(length)
+				.is_some_and(|end| end <= self.length),
+			"Range out of bounds"
+		);
+
+		let page_size = page_size();
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let (start, length) = (offset - offset % page_size, length + offset % page_size);
+
+		/* the range is part of our mapping, and must not be unmapped */
+		let range = ManuallyDrop::new(Map {
+			/* Safety: the range is within the mapping */
+			addr: unsafe { self.addr.cast::<u8>().add(start) }.cast(),
+			length,
+			phantom: PhantomData
+		});
+
+		/* Safety: the range is within the mapping, which we own */
+		unsafe { range.sync(SyncFlag::Sync.into()) }
+	}
+
 	/// # Safety
 	/// see `madvise`
 	pub unsafe fn advise(&self, advice: Advice) -> OsResult<()> {
@@ -349,3 +430,108 @@ impl Drop for Map<'_> {
 		unsafe { munmap(self.section()) }.expect_nounwind("Failed to unmap memory");
 	}
 }
+
+/// Returns the size of a page of memory
+#[must_use]
+pub fn page_size() -> usize {
+	get_system_configuration(SystemConfiguration::Pagesize)
+		.ok()
+		.flatten()
+		.and_then(|size| size.try_into().ok())
+		.unwrap_or(4096)
+}
+
+/// A sealable in-memory file that can be mapped by multiple processes
+///
+/// Other processes receive the file descriptor, for example through a unix
+/// socket or by inheriting it, and open it with [`SharedMemory::from_fd`].
+/// Sealing the memory with [`Seal::Shrink`] guarantees that receivers can
+/// access their mappings without the file being truncated underneath them.
+pub struct SharedMemory {
+	fd: OwnedFd
+}
+
+impl SharedMemory {
+	/// Create shared memory of `size` bytes, filled with zeroes
+	pub fn new(name: &CStr, size: usize) -> OsResult<Self> {
+		let fd = memfd_create(name, MemfdFlag::CloseOnExec | MemfdFlag::AllowSealing)?;
+		let this = Self { fd };
+
+		this.set_size(size)?;
+
+		Ok(this)
+	}
+
+	#[must_use]
+	pub const fn from_fd(fd: OwnedFd) -> Self {
+		Self { fd }
+	}
+
+	#[must_use]
+	pub fn fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+
+	#[must_use]
+	pub fn into_fd(self) -> OwnedFd {
+		self.fd
+	}
+
+	pub fn size(&self) -> OsResult<usize> {
+		let mut statx = Statx::default();
+
+		statx_fd(self.fd(), 0, StatxMask::Size as u32, &mut statx)?;
+
+		#[allow(clippy::cast_possible_truncation)]
+		Ok(statx.size as usize)
+	}
+
+	/// Resize the memory, filling any new space with zeroes
+	pub fn set_size(&self, size: usize) -> OsResult<()> {
+		#[allow(clippy::cast_possible_wrap)]
+		ftruncate(self.fd(), size as i64)
+	}
+
+	pub fn seals(&self) -> OsResult<BitFlags<Seal>> {
+		get_seals(self.fd())
+	}
+
+	/// Add `seals` to the memory. Seals cannot be removed
+	///
+	/// See [`Seal`] for more information
+	pub fn add_seals<S>(&self, seals: S) -> OsResult<()>
+	where
+		S: Into<BitFlags<Seal>>
+	{
+		add_seals(self.fd(), seals.into())
+	}
+
+	/// Map the entire memory, sharing changes with every other mapping
+	pub fn map(&self, prot: BitFlags<Protection>) -> OsResult<Map<'static>> {
+		Map::map_shared(self.fd(), 0, self.size()?, prot)
+	}
+}
+
+impl AsFd for SharedMemory {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd()
+	}
+}
+
+impl AsRawFd for SharedMemory {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl From<OwnedFd> for SharedMemory {
+	fn from(fd: OwnedFd) -> Self {
+		Self::from_fd(fd)
+	}
+}
+
+impl From<SharedMemory> for OwnedFd {
+	fn from(memory: SharedMemory) -> Self {
+		memory.into_fd()
+	}
+}
@@ -22,15 +22,20 @@ pub mod eventfd;
 pub mod fcntl;
 pub mod futex;
 pub mod inet;
+pub mod inotify;
 pub mod io_uring;
 pub mod iovec;
 pub mod mman;
 pub mod openat;
 pub mod openat2;
 pub mod poll;
+pub mod process;
 pub mod resource;
+pub mod sched;
 pub mod signal;
+pub mod signalfd;
 pub mod socket;
+pub mod splice;
 pub mod stat;
 pub mod syscall;
 pub mod tcp;
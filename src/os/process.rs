@@ -0,0 +1,204 @@
+use std::hint::unreachable_unchecked;
+
+use super::error::result_from_libc;
+use super::resource::Usage;
+use super::signal::{SigInfo, Signal};
+use super::*;
+
+define_enum! {
+	#[bitflags]
+	#[repr(u64)]
+	pub enum CloneFlag {
+		/// Share the memory space with the parent
+		Vm            = 1 << 8,
+
+		/// Share filesystem information (root, cwd, umask)
+		Fs            = 1 << 9,
+
+		/// Share the file descriptor table
+		Files         = 1 << 10,
+
+		/// Share signal handlers
+		SigHand       = 1 << 11,
+
+		/// Allocate a pidfd for the child, stored in [`CloneArgs::pidfd`]
+		PidFd         = 1 << 12,
+		PTrace        = 1 << 13,
+
+		/// Suspend the parent until the child execs or exits
+		VFork         = 1 << 14,
+		Parent        = 1 << 15,
+		Thread        = 1 << 16,
+		NewNs         = 1 << 17,
+		SysVSem       = 1 << 18,
+		SetTls        = 1 << 19,
+		ParentSetTid  = 1 << 20,
+		ChildClearTid = 1 << 21,
+		Detached      = 1 << 22,
+		Untraced      = 1 << 23,
+		ChildSetTid   = 1 << 24,
+		NewCGroup     = 1 << 25,
+		NewUts        = 1 << 26,
+		NewIpc        = 1 << 27,
+		NewUser       = 1 << 28,
+		NewPid        = 1 << 29,
+		NewNet        = 1 << 30,
+		Io            = 1 << 31,
+
+		/// Reset all signal handlers to their default in the child
+		ClearSigHand  = 1 << 32,
+
+		/// Spawn the child into the cgroup referred to by
+		/// [`CloneArgs::cgroup`]
+		IntoCGroup    = 1 << 33
+	}
+}
+
+define_struct! {
+	pub struct CloneArgs {
+		pub flags: u64,
+		pub pidfd: u64,
+		pub child_tid: u64,
+		pub parent_tid: u64,
+		pub exit_signal: u64,
+		pub stack: u64,
+		pub stack_size: u64,
+		pub tls: u64,
+		pub set_tid: u64,
+		pub set_tid_size: u64,
+		pub cgroup: u64
+	}
+}
+
+pub mod raw {
+	use super::*;
+
+	#[syscall_define(Execve)]
+	pub unsafe fn execve(path: &CStr, argv: Ptr<Ptr<()>>, envp: Ptr<Ptr<()>>) -> OsResult<()>;
+
+	#[syscall_define(ExitGroup)]
+	pub fn exit_group(code: i32) -> OsResult<()>;
+}
+
+/// Creates a new process, returning the pid of the child in the parent and
+/// zero in the child
+///
+/// # Safety
+/// The child shares whatever resources `args` specifies with the parent. With
+/// [`CloneFlag::VFork`] and [`CloneFlag::Vm`], the child must not return from
+/// the calling function, and may only call async-signal-safe functions before
+/// exec or exit
+#[syscall_define(Clone3)]
+pub unsafe fn clone3(args: &CloneArgs, size: usize) -> OsResult<i32>;
+
+extern "C" {
+	#[link_name = "clone"]
+	fn libc_clone(
+		func: unsafe extern "C" fn(MutPtr<()>) -> i32, stack: MutPtr<()>, flags: i32,
+		arg: MutPtr<()>, ...
+	) -> i32;
+}
+
+/// Creates a new process that runs `func(arg)` on `stack`, returning the pid
+/// of the child
+///
+/// Unlike [`clone3`], the child starts in `func` on its own stack, so it can
+/// share memory with a parent suspended by [`CloneFlag::VFork`]. With
+/// [`CloneFlag::PidFd`], the pidfd is stored in `pidfd`. Only the flags in the
+/// lower 32 bits are accepted
+///
+/// # Safety
+/// `stack` must be the top of a stack that stays valid until the child execs
+/// or exits. The child shares whatever `flags` specifies with the parent, and
+/// with [`CloneFlag::Vm`], may only call async-signal-safe functions before
+/// exec or exit
+pub unsafe fn clone_on_stack(
+	func: unsafe extern "C" fn(MutPtr<()>) -> i32, stack: MutPtr<()>, flags: BitFlags<CloneFlag>,
+	exit_signal: Signal, arg: MutPtr<()>, pidfd: MutPtr<i32>
+) -> OsResult<i32> {
+	let flags = u32::try_from(flags.bits()).map_err(|_| OsError::Inval)?;
+
+	#[allow(clippy::cast_possible_wrap)]
+	let flags = flags as i32 | exit_signal as i32;
+
+	/* Safety: guaranteed by caller */
+	let result = unsafe { libc_clone(func, stack, flags, arg, pidfd) };
+
+	#[allow(clippy::cast_possible_truncation)]
+	result_from_libc(result as isize).map(|pid| pid as i32)
+}
+
+/// Replaces the current process image. Only returns on error
+///
+/// # Safety
+/// `argv` and `envp` must be null terminated arrays of valid null terminated
+/// strings
+pub unsafe fn execve(path: &CStr, argv: Ptr<Ptr<()>>, envp: Ptr<Ptr<()>>) -> OsError {
+	/* Safety: guaranteed by caller */
+	match unsafe { raw::execve(path, argv, envp) } {
+		Ok(()) => OsError::Inval,
+		Err(err) => err
+	}
+}
+
+/// Terminates all threads in the calling process
+pub fn exit_process(code: i32) -> ! {
+	let _ = raw::exit_group(code);
+
+	/* Safety: exit_group never returns */
+	unsafe { unreachable_unchecked() }
+}
+
+define_enum! {
+	#[repr(u32)]
+	pub enum IdType {
+		All   = 0,
+		Pid   = 1,
+		PGid  = 2,
+		PidFd = 3
+	}
+}
+
+define_enum! {
+	#[bitflags]
+	#[repr(u32)]
+	pub enum WaitOption {
+		/// Return immediately if no child has changed state
+		NoHang    = 1 << 0,
+		Stopped   = 1 << 1,
+		Exited    = 1 << 2,
+		Continued = 1 << 3,
+
+		/// Leave the child in a waitable state
+		NoWait    = 1 << 24
+	}
+}
+
+define_enum! {
+	#[repr(i32)]
+	pub enum ChildCode {
+		Exited = 1,
+		Killed,
+		Dumped,
+		Trapped,
+		Stopped,
+		Continued
+	}
+}
+
+#[syscall_define(Waitid)]
+pub fn waitid(
+	id_type: IdType, id: i32, info: &mut SigInfo, options: BitFlags<WaitOption>,
+	usage: Option<&mut Usage>
+) -> OsResult<()>;
+
+#[syscall_define(PidfdOpen)]
+pub fn pidfd_open(pid: i32, flags: u32) -> OsResult<OwnedFd>;
+
+#[syscall_define(PidfdSendSignal)]
+pub fn pidfd_send_signal(
+	pidfd: BorrowedFd<'_>, signal: Signal, info: Option<&SigInfo>, flags: u32
+) -> OsResult<()>;
+
+#[syscall_define(Kill)]
+pub fn kill(pid: i32, signal: Signal) -> OsResult<()>;
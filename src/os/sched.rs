@@ -0,0 +1,108 @@
+use super::*;
+
+/// The maximum number of CPUs a [`CpuSet`] can describe
+pub const CPU_SETSIZE: usize = 1024;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+define_struct! {
+	pub struct CpuSet {
+		pub bits: [u64; CPU_SETSIZE / BITS_PER_WORD]
+	}
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+impl CpuSet {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a set containing only `cpu`
+	///
+	/// # Panics
+	/// if `cpu >= CPU_SETSIZE`
+	#[must_use]
+	pub fn single(cpu: usize) -> Self {
+		let mut this = Self::new();
+
+		this.set(cpu);
+		this
+	}
+
+	/// # Panics
+	/// if `cpu >= CPU_SETSIZE`
+	pub fn set(&mut self, cpu: usize) {
+		assert!(cpu < CPU_SETSIZE);
+
+		self.bits[cpu / BITS_PER_WORD] |= 1 << (cpu % BITS_PER_WORD);
+	}
+
+	/// # Panics
+	/// if `cpu >= CPU_SETSIZE`
+	pub fn clear(&mut self, cpu: usize) {
+		assert!(cpu < CPU_SETSIZE);
+
+		self.bits[cpu / BITS_PER_WORD] &= !(1 << (cpu % BITS_PER_WORD));
+	}
+
+	#[must_use]
+	pub const fn is_set(&self, cpu: usize) -> bool {
+		if cpu >= CPU_SETSIZE {
+			return false;
+		}
+
+		self.bits[cpu / BITS_PER_WORD] & (1 << (cpu % BITS_PER_WORD)) != 0
+	}
+
+	#[must_use]
+	pub fn count(&self) -> usize {
+		self.bits
+			.iter()
+			.map(|word| word.count_ones() as usize)
+			.sum()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+		(0..CPU_SETSIZE).filter(|cpu| self.is_set(*cpu))
+	}
+}
+
+impl FromIterator<usize> for CpuSet {
+	fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+		let mut this = Self::new();
+
+		for cpu in iter {
+			this.set(cpu);
+		}
+
+		this
+	}
+}
+
+pub mod raw {
+	use super::*;
+
+	#[syscall_define(SchedSetaffinity)]
+	pub fn sched_setaffinity(pid: i32, len: usize, mask: &CpuSet) -> OsResult<()>;
+
+	#[syscall_define(SchedGetaffinity)]
+	pub fn sched_getaffinity(pid: i32, len: usize, mask: &mut CpuSet) -> OsResult<usize>;
+}
+
+/// Sets the CPU affinity of the thread `pid`, or the calling thread if `None`
+pub fn set_affinity(pid: Option<i32>, set: &CpuSet) -> OsResult<()> {
+	raw::sched_setaffinity(pid.unwrap_or(0), size_of::<CpuSet>(), set)
+}
+
+/// Gets the CPU affinity of the thread `pid`, or the calling thread if `None`
+pub fn get_affinity(pid: Option<i32>) -> OsResult<CpuSet> {
+	let mut set = CpuSet::default();
+
+	raw::sched_getaffinity(pid.unwrap_or(0), size_of::<CpuSet>(), &mut set)?;
+
+	Ok(set)
+}
+
+#[syscall_define(SchedYield)]
+pub fn sched_yield() -> OsResult<()>;
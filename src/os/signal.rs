@@ -295,6 +295,42 @@ define_enum! {
 	}
 }
 
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum SignalStackFlag {
+		/// The thread is currently running on the alternate stack
+		OnStack    = 1 << 0,
+
+		/// The alternate stack is disabled
+		Disable    = 1 << 1,
+
+		/// Disable the alternate stack while a handler is running on it
+		AutoDisarm = 1 << 31
+	}
+}
+
+define_struct! {
+	pub struct SignalStack {
+		pub sp: MutPtr<()>,
+		pub flags: u32,
+		pub size: usize
+	}
+}
+
+/// The minimum size of an alternate signal stack
+pub const MIN_SIGNAL_STACK_SIZE: usize = 0x2000;
+
+/// Set and/or get the alternate stack used by handlers installed with
+/// [`SignalFlags::OnStack`] on the calling thread
+///
+/// # Safety
+/// The new stack must remain valid until it is replaced or disabled
+#[syscall_define(Sigaltstack)]
+pub unsafe fn sigaltstack(
+	stack: Option<&SignalStack>, old: Option<&mut SignalStack>
+) -> OsResult<()>;
+
 extern "C" {
 	fn sigaction(num: i32, action: Ptr<SigAction>, old: MutPtr<SigAction>) -> i32;
 
@@ -0,0 +1,165 @@
+use super::fcntl::OpenFlag;
+use super::signal::*;
+use super::unistd::*;
+use super::*;
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum CreateFlag {
+		NonBlock    = OpenFlag::NonBlock as u32,
+		CloseOnExec = OpenFlag::CloseOnExec as u32
+	}
+}
+
+define_struct! {
+	pub struct SignalFdInfo {
+		pub signal: u32,
+		pub errno: i32,
+		pub code: i32,
+		pub pid: u32,
+		pub uid: u32,
+		pub fd: i32,
+		pub tid: u32,
+		pub band: u32,
+		pub overrun: u32,
+		pub trapno: u32,
+		pub status: i32,
+		pub int: i32,
+		pub ptr: u64,
+		pub utime: u64,
+		pub stime: u64,
+		pub addr: u64,
+		pub addr_lsb: u16,
+		pub pad2: u16,
+		pub syscall: i32,
+		pub call_addr: u64,
+		pub arch: u32,
+		pub pad: [u8; 28]
+	}
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+impl From<SignalFdInfo> for SigInfo {
+	fn from(value: SignalFdInfo) -> Self {
+		let mut info = Self {
+			signal: value.signal as i32,
+			errno: value.errno,
+			code: value.code,
+			..Default::default()
+		};
+
+		let signal = Signal::from_u32(value.signal);
+
+		info.fields = match signal {
+			Some(Signal::Child) => SigFields {
+				child: SigChild {
+					pid: value.pid as i32,
+					uidi: value.uid,
+					status: value.status,
+					utime: value.utime as i64,
+					stime: value.stime as i64
+				}
+			},
+
+			Some(
+				Signal::SegmentationViolation |
+				Signal::Bus |
+				Signal::IllegalInstruction |
+				Signal::FloatingPointException |
+				Signal::Trap
+			) => SigFields {
+				fault: SigFault {
+					addr: MutPtr::from_addr(value.addr as usize),
+					info: SigFaultInfo { addr_lsb: value.addr_lsb as i16 }
+				}
+			},
+
+			Some(Signal::Io) => SigFields {
+				poll: SigPoll { band: i64::from(value.band), fd: value.fd }
+			},
+
+			Some(Signal::Syscall) => SigFields {
+				sys: SigSys {
+					addr: MutPtr::from_addr(value.call_addr as usize),
+					syscall: value.syscall,
+					arch: value.arch
+				}
+			},
+
+			_ => SigFields {
+				rt: SigRt {
+					pid: value.pid as i32,
+					uid: value.uid,
+					sigval: SigVal { ptr: MutPtr::from_addr(value.ptr as usize) }
+				}
+			}
+		};
+
+		info
+	}
+}
+
+pub mod raw {
+	use super::*;
+
+	#[syscall_define(Signalfd4)]
+	pub fn signalfd4(
+		fd: RawFd, #[array] mask: RawBuf<'_>, flags: BitFlags<CreateFlag>
+	) -> OsResult<RawFd>;
+}
+
+/// Returns the [`SignalSet`] containing only `signal`
+#[must_use]
+#[allow(clippy::arithmetic_side_effects, clippy::cast_sign_loss)]
+pub const fn signal_bit(signal: i32) -> SignalSet {
+	1 << (signal - 1) as u32
+}
+
+/// Returns the [`SignalSet`] containing all of `signals`
+#[must_use]
+pub fn signal_set(signals: &[Signal]) -> SignalSet {
+	signals
+		.iter()
+		.fold(0, |set, signal| set | signal_bit(*signal as i32))
+}
+
+pub struct SignalFd(OwnedFd);
+
+impl SignalFd {
+	/// Create a signalfd accepting the signals in `mask`
+	///
+	/// The signals must be blocked on all threads for them to be queued to the
+	/// signalfd rather than handled normally
+	pub fn new(mask: SignalSet, flags: BitFlags<CreateFlag>) -> OsResult<Self> {
+		let fd = raw::signalfd4(INVALID_FD, Some(&[mask][..]).into(), flags)?;
+
+		/* Safety: the kernel returned a new file descriptor */
+		Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+	}
+
+	/// Replace the set of signals accepted by this signalfd
+	pub fn set_mask(&self, mask: SignalSet) -> OsResult<()> {
+		raw::signalfd4(
+			self.0.as_raw_fd(),
+			Some(&[mask][..]).into(),
+			BitFlags::default()
+		)?;
+
+		Ok(())
+	}
+
+	/// Read a pending signal, blocking unless the signalfd is non blocking
+	pub fn read(&self) -> OsResult<SignalFdInfo> {
+		let mut info = SignalFdInfo::default();
+
+		read(self.0.as_fd(), (&mut info).into())?;
+
+		Ok(info)
+	}
+
+	#[must_use]
+	pub fn fd(&self) -> BorrowedFd<'_> {
+		self.0.as_fd()
+	}
+}
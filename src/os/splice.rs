@@ -0,0 +1,66 @@
+use super::*;
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum SpliceFlag {
+		/// Hint to move pages instead of copying
+		Move     = 1 << 0,
+
+		/// Do not block on pipe I/O
+		NonBlock = 1 << 1,
+
+		/// More data will be spliced in a subsequent call
+		More     = 1 << 2,
+
+		/// Unused for splice and tee
+		Gift     = 1 << 3
+	}
+}
+
+/// Move up to `len` bytes from `fd_in` to `fd_out`, at least one of which
+/// must be a pipe, without copying through user space
+///
+/// If an offset is given for a non-pipe descriptor, data is transferred
+/// starting at that offset, which is then advanced. Otherwise, the file
+/// offset is used and updated.
+///
+/// Returns the number of bytes moved, or zero at end of input
+#[syscall_define(Splice)]
+pub fn splice(
+	fd_in: BorrowedFd<'_>, off_in: Option<&mut i64>, fd_out: BorrowedFd<'_>,
+	off_out: Option<&mut i64>, len: usize, flags: BitFlags<SpliceFlag>
+) -> OsResult<usize>;
+
+/// Duplicate up to `len` bytes from the pipe `fd_in` to the pipe `fd_out`,
+/// without consuming them from `fd_in`
+///
+/// Returns the number of bytes duplicated
+#[syscall_define(Tee)]
+pub fn tee(
+	fd_in: BorrowedFd<'_>, fd_out: BorrowedFd<'_>, len: usize, flags: BitFlags<SpliceFlag>
+) -> OsResult<usize>;
+
+/// Copy up to `count` bytes from the file `in_fd` to `out_fd` within the
+/// kernel
+///
+/// If `offset` is given, data is read starting at that offset, which is then
+/// advanced, and the file offset of `in_fd` is left unchanged
+///
+/// Returns the number of bytes copied, or zero at end of input
+#[syscall_define(Sendfile)]
+pub fn sendfile(
+	out_fd: BorrowedFd<'_>, in_fd: BorrowedFd<'_>, offset: Option<&mut i64>, count: usize
+) -> OsResult<usize>;
+
+/// Copy up to `len` bytes between two regular files within the kernel,
+/// possibly sharing extents on filesystems that support it
+///
+/// Offsets behave the same as for [`splice`]. `flags` must be zero
+///
+/// Returns the number of bytes copied, or zero at end of input
+#[syscall_define(CopyFileRange)]
+pub fn copy_file_range(
+	fd_in: BorrowedFd<'_>, off_in: Option<&mut i64>, fd_out: BorrowedFd<'_>,
+	off_out: Option<&mut i64>, len: usize, flags: u32
+) -> OsResult<usize>;
@@ -1,4 +1,6 @@
 use super::error::result_from_libc;
+use super::fcntl::OpenFlag;
+use super::unistd::read;
 use super::*;
 use crate::impls::OptionExt;
 
@@ -117,3 +119,156 @@ pub fn nanotime(clock: ClockId) -> Result<u64> {
 	/* time on linux fits into a u64 */
 	Ok(nanos.expect_nounwind("Failed to get the time"))
 }
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum SleepFlag {
+		/// The requested time is an absolute time on the clock
+		Absolute = 1 << 0
+	}
+}
+
+/// Sleep on `clock` until `request` elapses, or until the absolute time
+/// `request` if [`SleepFlag::Absolute`] is set
+///
+/// If interrupted by a signal, the remaining time of a relative sleep is
+/// written to `remain`
+#[syscall_define(ClockNanosleep)]
+pub fn clock_nanosleep(
+	clock: ClockId, flags: BitFlags<SleepFlag>, request: &TimeSpec, remain: Option<&mut TimeSpec>
+) -> OsResult<()>;
+
+pub mod raw {
+	use super::*;
+
+	#[syscall_define(ClockGetres)]
+	pub fn clock_getres(clock: ClockId, res: &mut TimeSpec) -> OsResult<()>;
+}
+
+/// Returns the resolution of `clock`
+pub fn clock_getres(clock: ClockId) -> OsResult<TimeSpec> {
+	let mut res = TimeSpec::zero();
+
+	raw::clock_getres(clock, &mut res)?;
+
+	Ok(res)
+}
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum TimerFdFlag {
+		NonBlock    = OpenFlag::NonBlock as u32,
+		CloseOnExec = OpenFlag::CloseOnExec as u32
+	}
+}
+
+define_enum! {
+	#[repr(u32)]
+	#[bitflags]
+	pub enum TimerSetFlag {
+		/// The expiration is an absolute time on the timer's clock
+		Absolute    = 1 << 0,
+
+		/// For absolute real time timers, fail reads with
+		/// [`OsError::Canceled`] if the clock is set discontinuously
+		///
+		/// [`OsError::Canceled`]: super::error::OsError::Canceled
+		CancelOnSet = 1 << 1
+	}
+}
+
+define_struct! {
+	pub struct ITimerSpec {
+		/// The period of the timer, or zero for a one shot timer
+		pub interval: TimeSpec,
+
+		/// The time until the next expiration, or zero if disarmed
+		pub value: TimeSpec
+	}
+}
+
+#[syscall_define(TimerfdCreate)]
+pub fn timerfd_create(clock: ClockId, flags: BitFlags<TimerFdFlag>) -> OsResult<OwnedFd>;
+
+#[syscall_define(TimerfdSettime)]
+pub fn timerfd_settime(
+	fd: BorrowedFd<'_>, flags: BitFlags<TimerSetFlag>, new_value: &ITimerSpec,
+	old_value: Option<&mut ITimerSpec>
+) -> OsResult<()>;
+
+#[syscall_define(TimerfdGettime)]
+pub fn timerfd_gettime(fd: BorrowedFd<'_>, value: &mut ITimerSpec) -> OsResult<()>;
+
+/// A timer that delivers expirations through a file descriptor, so that it
+/// can be waited on with poll, epoll or io_uring
+pub struct TimerFd(OwnedFd);
+
+impl TimerFd {
+	pub fn new(clock: ClockId, flags: BitFlags<TimerFdFlag>) -> OsResult<Self> {
+		timerfd_create(clock, flags).map(Self)
+	}
+
+	/// Arm the timer to first expire after `value`, then every `interval`
+	/// if it is non zero. A zero `value` disarms the timer
+	pub fn set(&self, value: Duration, interval: Duration) -> OsResult<()> {
+		let spec = ITimerSpec {
+			interval: TimeSpec::from_duration(interval),
+			value: TimeSpec::from_duration(value)
+		};
+
+		timerfd_settime(self.fd(), BitFlags::default(), &spec, None)
+	}
+
+	/// Arm the timer with `spec`, returning the previous setting
+	pub fn set_spec(
+		&self, flags: BitFlags<TimerSetFlag>, spec: &ITimerSpec
+	) -> OsResult<ITimerSpec> {
+		let mut old = ITimerSpec::default();
+
+		timerfd_settime(self.fd(), flags, spec, Some(&mut old))?;
+
+		Ok(old)
+	}
+
+	pub fn disarm(&self) -> OsResult<()> {
+		self.set(Duration::ZERO, Duration::ZERO)
+	}
+
+	/// Returns the current setting, with `value` relative to now
+	pub fn get(&self) -> OsResult<ITimerSpec> {
+		let mut spec = ITimerSpec::default();
+
+		timerfd_gettime(self.fd(), &mut spec)?;
+
+		Ok(spec)
+	}
+
+	/// Read the number of expirations since the last read, blocking until
+	/// the timer expires unless the timer is non blocking
+	pub fn read(&self) -> OsResult<u64> {
+		let mut bytes = 0u64.to_ne_bytes();
+
+		read(self.fd(), (&mut bytes).into())?;
+
+		Ok(u64::from_ne_bytes(bytes))
+	}
+
+	#[must_use]
+	pub fn fd(&self) -> BorrowedFd<'_> {
+		self.0.as_fd()
+	}
+}
+
+impl AsFd for TimerFd {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd()
+	}
+}
+
+impl AsRawFd for TimerFd {
+	fn as_raw_fd(&self) -> RawFd {
+		self.0.as_raw_fd()
+	}
+}
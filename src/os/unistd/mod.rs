@@ -1,4 +1,5 @@
 use super::error::*;
+use super::fcntl::OpenFlag;
 use super::openat::into_raw_dirfd;
 use super::openat2::OpenHow;
 use super::*;
@@ -15,6 +16,9 @@ pub mod internal {
 
 	#[syscall_define(Openat2)]
 	pub fn openat2(dirfd: RawFd, filename: &CStr, how: &OpenHow, size: usize) -> OsResult<OwnedFd>;
+
+	#[syscall_define(Pipe2)]
+	pub fn pipe2(fds: &mut [RawFd; 2], flags: BitFlags<OpenFlag>) -> OsResult<()>;
 }
 
 #[syscall_define(Open)]
@@ -38,7 +42,43 @@ pub fn openat2(dirfd: Option<BorrowedFd<'_>>, filename: &CStr, how: &OpenHow) ->
 pub fn close(fd: OwnedFd) -> OsResult<()>;
 
 #[syscall_define(Read)]
-pub fn read(fd: BorrowedFd<'_>, #[array] buf: MutRawBuf<'_>) -> OsResult<()>;
+pub fn read(fd: BorrowedFd<'_>, #[array] buf: MutRawBuf<'_>) -> OsResult<usize>;
 
 #[syscall_define(Write)]
-pub fn write(fd: BorrowedFd<'_>, #[array] buf: RawBuf<'_>) -> OsResult<()>;
+pub fn write(fd: BorrowedFd<'_>, #[array] buf: RawBuf<'_>) -> OsResult<usize>;
+
+/// Creates a pipe, returning the read and write ends
+///
+/// Only [`OpenFlag::CloseOnExec`], [`OpenFlag::NonBlock`] and
+/// [`OpenFlag::Direct`] are valid flags
+pub fn pipe2(flags: BitFlags<OpenFlag>) -> OsResult<(OwnedFd, OwnedFd)> {
+	let mut fds = [INVALID_FD; 2];
+
+	internal::pipe2(&mut fds, flags)?;
+
+	/* Safety: the kernel returned two new file descriptors */
+	#[allow(clippy::multiple_unsafe_ops_per_block)]
+	Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// Duplicates `fd` onto the raw file descriptor `new_fd`, closing `new_fd`
+/// first if it was open
+///
+/// # Safety
+/// `new_fd` must not be owned by anything that expects it to remain the same
+/// file
+#[syscall_define(Dup3)]
+pub unsafe fn dup3(fd: BorrowedFd<'_>, new_fd: RawFd, flags: BitFlags<OpenFlag>)
+	-> OsResult<RawFd>;
+
+#[syscall_define(Ftruncate)]
+pub fn ftruncate(fd: BorrowedFd<'_>, length: i64) -> OsResult<()>;
+
+#[syscall_define(Chdir)]
+pub fn chdir(path: &CStr) -> OsResult<()>;
+
+#[syscall_define(Getpid)]
+pub fn getpid() -> OsResult<i32>;
+
+#[syscall_define(Gettid)]
+pub fn gettid() -> OsResult<i32>;
@@ -1,7 +1,10 @@
 use std::fmt::Arguments;
 use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::log::*;
+use crate::os::error::OsResult;
+use crate::os::signal::*;
 
 pub type MaybePanic<T> = std::thread::Result<T>;
 
@@ -45,3 +48,24 @@ where
 	#[cfg(not(debug_assertions))]
 	func()
 }
+
+/// Signals that every runtime thread keeps blocked, so that they are only
+/// received through a signalfd
+static RUNTIME_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/// Blocks `signals` on the calling thread, and adds them to the set that
+/// every [`Executor`] blocks on its thread the next time one of its workers
+/// suspends
+///
+/// [`Executor`]: crate::coroutines::Executor
+pub fn block_runtime_signals(signals: SignalSet) -> OsResult<()> {
+	RUNTIME_SIGNALS.fetch_or(signals, Ordering::Relaxed);
+
+	pthread_set_sigmask(SignalHow::Block, Some(&[signals]), None)
+}
+
+/// The signals added with [`block_runtime_signals`]
+#[must_use]
+pub fn runtime_signals() -> SignalSet {
+	RUNTIME_SIGNALS.load(Ordering::Relaxed)
+}
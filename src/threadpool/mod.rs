@@ -10,6 +10,7 @@ use crate::container::intrusive::linked_list::*;
 use crate::error::*;
 use crate::future::*;
 use crate::os::signal::*;
+use crate::os::signalfd::{signal_bit, signal_set};
 use crate::os::unistd::{get_system_configuration, SystemConfiguration};
 use crate::pointer::*;
 use crate::runtime::call_no_unwind;
@@ -118,7 +119,8 @@ struct Worker {
 	thread: AtomicU64,
 	queue: Pinned<Arc<Queue>>,
 	cur_work: UnsafeCell<Ptr<Work<'static>>>,
-	context: TaskContext
+	context: TaskContext,
+	block_signals: bool
 }
 
 /* Safety: internal use only */
@@ -128,12 +130,31 @@ unsafe impl Send for Worker {}
 unsafe impl Sync for Worker {}
 
 impl Worker {
-	const fn new(queue: Pinned<Arc<Queue>>) -> Self {
+	const fn new(queue: Pinned<Arc<Queue>>, block_signals: bool) -> Self {
 		Self {
 			thread: AtomicU64::new(0),
 			queue,
 			cur_work: UnsafeCell::new(Ptr::null()),
-			context: TaskContext::new()
+			context: TaskContext::new(),
+			block_signals
+		}
+	}
+
+	/// Workers only receive the interrupt signal and synchronous faults, so
+	/// that process directed signals are left for the threads that expect
+	/// them, such as readers of a signalfd
+	fn block_signals(&self) {
+		let unblocked = signal_bit(INTERRUPT_SIGNAL) |
+			signal_set(&[
+				Signal::SegmentationViolation,
+				Signal::Bus,
+				Signal::IllegalInstruction,
+				Signal::FloatingPointException,
+				Signal::Trap
+			]);
+
+		if let Err(err) = pthread_set_sigmask(SignalHow::SetMask, Some(&[!unblocked]), None) {
+			warn!(target: self, "== Failed to set worker signal mask: {:?}", err);
 		}
 	}
 
@@ -143,6 +164,10 @@ impl Worker {
 		clippy::missing_panics_doc
 	)]
 	fn run(&self) {
+		if self.block_signals {
+			self.block_signals();
+		}
+
 		let mut work_queue = self.queue.work.lock().unwrap();
 
 		self.queue.idle_count.fetch_sub(1, Ordering::Relaxed);
@@ -224,14 +249,29 @@ impl ThreadPool {
 		}
 	}
 
-	#[allow(clippy::missing_panics_doc, clippy::expect_used)]
+	/// Create a pool of `max_workers` threads, which inherit the signal mask
+	/// of the calling thread
 	pub fn new(max_workers: usize) -> Result<Self> {
+		Self::create(max_workers, false)
+	}
+
+	/// Create a pool of `max_workers` threads, which block every signal except
+	/// the ones used to interrupt them and synchronous faults
+	///
+	/// Process directed signals are then left for the threads that expect
+	/// them, such as readers of a signalfd
+	pub fn new_blocking_signals(max_workers: usize) -> Result<Self> {
+		Self::create(max_workers, true)
+	}
+
+	#[allow(clippy::missing_panics_doc, clippy::expect_used)]
+	fn create(max_workers: usize, block_signals: bool) -> Result<Self> {
 		let queue = Queue::new(max_workers).pin_arc();
 		let mut threads = Vec::with_capacity(max_workers);
 		let mut error = None;
 
 		for i in 0..max_workers {
-			let worker = Arc::new(Worker::new(queue.clone()));
+			let worker = Arc::new(Worker::new(queue.clone(), block_signals));
 			let worker_clone = worker.clone();
 
 			let result = thread::Builder::new()
@@ -282,15 +322,18 @@ impl ThreadPool {
 		Ok(this)
 	}
 
+	/// The default number of workers, twice the number of online cpus
 	#[allow(clippy::expect_used, clippy::missing_panics_doc)]
-	pub fn new_with_default_count() -> Result<Self> {
+	pub fn default_count() -> Result<usize> {
 		let count = get_system_configuration(SystemConfiguration::NprocessorsOnln)?
 			.expect("Falied to get cpu count");
-		let mut count = count.try_into().unwrap_or(usize::MAX);
+		let count: usize = count.try_into().unwrap_or(usize::MAX);
 
-		count = count.checked_mul(2).unwrap_or(usize::MAX);
+		Ok(count.checked_mul(2).unwrap_or(usize::MAX))
+	}
 
-		Self::new(count)
+	pub fn new_with_default_count() -> Result<Self> {
+		Self::new(Self::default_count()?)
 	}
 
 	/// # Safety
@@ -0,0 +1,78 @@
+use std::{env, fs, process};
+
+use xx_core::async_std::fs::*;
+
+use super::*;
+
+#[main]
+#[test]
+pub async fn test_watcher() -> Result<()> {
+	let dir = env::temp_dir().join(format!("xx-core-watcher-{}", process::id()));
+
+	fs::create_dir_all(&dir)?;
+
+	let mut watcher = Watcher::new()?;
+
+	watcher.add(&dir)?;
+
+	let file = dir.join("file");
+	let renamed = dir.join("renamed");
+
+	fs::write(&file, b"data")?;
+	fs::rename(&file, &renamed)?;
+	fs::remove_file(&renamed)?;
+
+	assert_eq!(watcher.recv().await?, WatchEvent::Create(file.clone()));
+	assert_eq!(watcher.recv().await?, WatchEvent::Modify(file.clone()));
+	assert_eq!(
+		watcher.recv().await?,
+		WatchEvent::Rename { from: file, to: renamed.clone() }
+	);
+	assert_eq!(watcher.recv().await?, WatchEvent::Delete(renamed));
+
+	watcher.remove(&dir)?;
+
+	assert_eq!(watcher.recv().await?, WatchEvent::Removed(dir.clone()));
+	assert!(watcher.paths().next().is_none());
+
+	fs::remove_dir(&dir)?;
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_watcher_shared_watch() -> Result<()> {
+	let dir = env::temp_dir().join(format!("xx-core-watcher-shared-{}", process::id()));
+	let link = env::temp_dir().join(format!("xx-core-watcher-link-{}", process::id()));
+
+	fs::create_dir_all(&dir)?;
+	std::os::unix::fs::symlink(&dir, &link)?;
+
+	let mut watcher = Watcher::new()?;
+
+	watcher.add(&dir)?;
+	watcher.add(&link)?;
+
+	assert_eq!(watcher.paths().count(), 2);
+
+	watcher.remove(&link)?;
+
+	assert_eq!(watcher.paths().collect::<Vec<_>>(), [dir.as_path()]);
+
+	let file = dir.join("file");
+
+	fs::create_dir(&file)?;
+
+	assert_eq!(watcher.recv().await?, WatchEvent::Create(file.clone()));
+
+	watcher.remove(&dir)?;
+
+	assert_eq!(watcher.recv().await?, WatchEvent::Removed(dir.clone()));
+
+	fs::remove_dir(&file)?;
+	fs::remove_file(&link)?;
+	fs::remove_dir(&dir)?;
+
+	Ok(())
+}
@@ -0,0 +1,59 @@
+use std::fs::{self, File};
+use std::os::fd::OwnedFd;
+use std::{env, process};
+
+use super::*;
+
+#[main]
+#[test]
+pub async fn test_copy() -> Result<()> {
+	let path = env::temp_dir().join(format!("xx-core-copy-{}", process::id()));
+
+	/* small enough to fit in the pipe's buffer */
+	let data: Vec<u8> = (0..30_000u32).map(|i| (i % 251) as u8).collect();
+
+	fs::write(&path, &data)?;
+
+	let mut file = AsyncFd::new(OwnedFd::from(File::open(&path)?));
+	let (mut reader, mut writer) = pipe()?;
+
+	assert_eq!(copy(&mut file, &mut writer).await? as usize, data.len());
+
+	let mut file = BufReader::new(AsyncFd::new(OwnedFd::from(File::open(&path)?)));
+
+	assert_eq!(copy_buf(&mut file, &mut writer).await? as usize, data.len());
+
+	drop(writer);
+
+	let mut buf = Vec::new();
+
+	reader.read_to_end(&mut buf).await?;
+
+	assert_eq!(buf.len(), data.len() * 2);
+	assert_eq!(&buf[..data.len()], data);
+	assert_eq!(&buf[data.len()..], data);
+
+	fs::remove_file(&path)?;
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_copy_procfs() -> Result<()> {
+	let expected = fs::read("/proc/self/cmdline")?;
+	let mut file = AsyncFd::new(OwnedFd::from(File::open("/proc/self/cmdline")?));
+	let (mut reader, mut writer) = pipe()?;
+
+	assert_eq!(copy(&mut file, &mut writer).await? as usize, expected.len());
+
+	drop(writer);
+
+	let mut buf = Vec::new();
+
+	reader.read_to_end(&mut buf).await?;
+
+	assert_eq!(buf, expected);
+
+	Ok(())
+}
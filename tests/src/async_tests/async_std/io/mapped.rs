@@ -0,0 +1,63 @@
+use std::fs::{self, File};
+use std::io::SeekFrom;
+use std::{env, process};
+
+use xx_core::os::mman::page_size;
+
+use super::*;
+
+#[main]
+#[test]
+pub async fn test_mapped_file() -> Result<()> {
+	let path = env::temp_dir().join(format!("xx-core-mapped-{}", process::id()));
+	let mut data = String::new();
+
+	for i in 0..2000 {
+		data.push_str(&format!("line {i}\n"));
+	}
+
+	fs::write(&path, &data)?;
+
+	/* use a tiny window so that reads cross several windows */
+	let mut file = MappedFile::with_window_size(File::open(&path)?.into(), page_size())?
+		.hint(MapHint::Sequential);
+
+	assert_eq!(file.stream_len().await?, data.len() as u64);
+
+	let mut contents = String::new();
+
+	file.read_to_string(&mut contents).await?;
+
+	assert_eq!(contents, data);
+	assert_eq!(file.read(&mut [0; 16]).await?, 0);
+
+	let mut line = String::new();
+
+	file.seek(SeekFrom::Start(5)).await?;
+	file.fill().await?;
+
+	/* the mapping starts at the page boundary, but only a window is buffered */
+	assert_eq!(file.buffer().len(), file.capacity());
+
+	file.read_line(&mut line).await?;
+
+	assert_eq!(line, "0");
+	assert_eq!(file.seek(SeekFrom::End(-10)).await?, data.len() as u64 - 10);
+
+	line.clear();
+	file.read_line(&mut line).await?;
+
+	assert_eq!(line, "line 1999");
+
+	file.seek(SeekFrom::Current(-10)).await?;
+
+	assert_eq!(file.stream_position().await?, data.len() as u64 - 10);
+	assert!(file
+		.seek(SeekFrom::Current(-(data.len() as i64)))
+		.await
+		.is_err());
+
+	fs::remove_file(&path)?;
+
+	Ok(())
+}
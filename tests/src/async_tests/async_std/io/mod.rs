@@ -1,3 +1,7 @@
 use super::*;
 
 mod buf_reader;
+mod copy;
+mod mapped;
+mod pipe;
+mod stdio;
@@ -0,0 +1,45 @@
+use std::os::fd::AsFd;
+
+use super::*;
+
+#[main]
+#[test]
+pub async fn test_pipe() -> Result<()> {
+	let (mut reader, mut writer) = pipe()?;
+	let mut buf = [0u8; 5];
+
+	writer.write_all(b"hello").await?;
+	reader.read_fully(&mut buf).await?;
+
+	assert_eq!(&buf, b"hello");
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_splice_tee() -> Result<()> {
+	let (mut first_reader, mut first_writer) = pipe()?;
+	let (mut second_reader, second_writer) = pipe()?;
+	let (mut third_reader, third_writer) = pipe()?;
+
+	first_writer.write_all(b"zero copy").await?;
+
+	let copied = tee(first_reader.as_fd(), third_writer.as_fd(), 9).await?;
+	let moved = splice(first_reader.as_fd(), None, second_writer.as_fd(), None, 9).await?;
+
+	assert_eq!((copied, moved), (9, 9));
+
+	drop((first_writer, second_writer, third_writer));
+
+	let mut buf = Vec::new();
+
+	assert_eq!(first_reader.read_to_end(&mut buf).await?, 0);
+
+	second_reader.read_to_end(&mut buf).await?;
+	third_reader.read_to_end(&mut buf).await?;
+
+	assert_eq!(buf, b"zero copyzero copy");
+
+	Ok(())
+}
@@ -0,0 +1,55 @@
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+
+use xx_core::enumflags2::BitFlags;
+use xx_core::os::unistd::dup3;
+
+use super::*;
+
+/// Puts the saved stdin back, even if the test returns early
+struct RestoreStdin(OwnedFd);
+
+impl Drop for RestoreStdin {
+	fn drop(&mut self) {
+		unsafe { dup3(self.0.as_fd(), 0, BitFlags::default()) }.unwrap();
+	}
+}
+
+#[main]
+#[test]
+pub async fn test_stdio() -> Result<()> {
+	let mut err = stderr();
+
+	assert_eq!(err.write(b"stderr\n").await?, 7);
+
+	let mut lock = stdout().lock().await?;
+
+	lock.write_all(b"stdout ").await?;
+	lock.write_all(b"locked\n").await?;
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_stdin() -> Result<()> {
+	let (reader, mut writer) = pipe()?;
+	let _restore = RestoreStdin(unsafe { BorrowedFd::borrow_raw(0) }.try_clone_to_owned()?);
+
+	/* nothing else in the tests reads stdin, so it can be swapped out */
+	unsafe { dup3(reader.as_fd(), 0, BitFlags::default()) }?;
+	drop(reader);
+
+	writer.write_all(b"first\nsecond\n").await?;
+	drop(writer);
+
+	let input = stdin();
+	let mut line = String::new();
+
+	input.read_line(&mut line).await?;
+	input.read_line(&mut line).await?;
+
+	assert_eq!(line, "first\nsecond\n");
+	assert_eq!(input.lock().await?.read_to_end(&mut Vec::new()).await?, 0);
+
+	Ok(())
+}
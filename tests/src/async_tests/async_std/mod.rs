@@ -1,4 +1,7 @@
 use super::*;
 
+mod fs;
 mod io;
+mod process;
+mod signal;
 mod sync;
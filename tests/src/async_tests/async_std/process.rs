@@ -0,0 +1,44 @@
+use xx_core::async_std::process::*;
+
+use super::*;
+
+#[main]
+#[test]
+pub async fn test_process_output() -> Result<()> {
+	let output = Command::new("sh")
+		.args(["-c", "echo $GREETING; echo err >&2; exit 3"])
+		.env("GREETING", "hello")
+		.output()
+		.await?;
+
+	assert_eq!(output.status.code(), Some(3));
+	assert_eq!(output.stdout, b"hello\n");
+	assert_eq!(output.stderr, b"err\n");
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_process_piped() -> Result<()> {
+	let mut child = Command::new("cat")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()?;
+
+	child.stdin().unwrap().write_all(b"piped").await?;
+
+	let output = child.wait_with_output().await?;
+
+	assert!(output.status.success());
+	assert_eq!(output.stdout, b"piped");
+
+	let mut child = Command::new("sleep").arg("10").spawn()?;
+
+	child.kill()?;
+
+	assert_eq!(child.wait().await?.signal(), Some(9));
+	assert!(Command::new("does-not-exist").spawn().is_err());
+
+	Ok(())
+}
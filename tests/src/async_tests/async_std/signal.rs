@@ -0,0 +1,51 @@
+use std::os::unix::thread::{JoinHandleExt, RawPthread};
+use std::sync::mpsc::channel;
+use std::thread;
+
+use xx_core::async_std::signal::*;
+use xx_core::async_std::AsyncIterator;
+use xx_core::os::signal::{pthread_signal, Signal};
+
+use super::*;
+
+extern "C" {
+	fn pthread_self() -> RawPthread;
+}
+
+#[main]
+#[test]
+pub async fn test_signal_stream() -> Result<()> {
+	let mut signals = signal(Signal::User2)?;
+
+	for _ in 0..2 {
+		pthread_signal(unsafe { pthread_self() }, Signal::User2 as i32)?;
+
+		let info = signals.next().await.unwrap()?;
+
+		assert_eq!(info.signal, Signal::User2 as i32);
+	}
+
+	Ok(())
+}
+
+#[main]
+#[test]
+pub async fn test_signal_forwarded() -> Result<()> {
+	let (sender, receiver) = channel::<()>();
+
+	/* started before the stream, so the signal is still unblocked there */
+	let thread = thread::spawn(move || receiver.recv());
+	let mut signals = signal(Signal::User1)?;
+
+	pthread_signal(thread.as_pthread_t(), Signal::User1 as i32)?;
+
+	let info = signals.recv().await?;
+
+	assert_eq!(info.signal, Signal::User1 as i32);
+
+	drop(sender);
+
+	thread.join().unwrap().unwrap_err();
+
+	Ok(())
+}
@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use xx_core::async_std::sync::futex::*;
+use xx_core::error::Result;
+use xx_pulse::*;
+
+#[main]
+#[test]
+pub async fn test_futex() -> Result<()> {
+	let word = Arc::new(AtomicU32::new(0));
+
+	assert!(!futex_wait(&word, 1).await?);
+	assert_eq!(
+		futex_wait_timeout(&word, 0, Duration::from_millis(10)).await?,
+		None
+	);
+
+	let waker = {
+		let word = word.clone();
+
+		thread::spawn(move || {
+			thread::sleep(Duration::from_millis(50));
+
+			word.store(1, Ordering::Release);
+			futex_wake_all(&word).unwrap();
+		})
+	};
+
+	while word.load(Ordering::Acquire) == 0 {
+		futex_wait(&word, 0).await?;
+	}
+
+	waker.join().unwrap();
+
+	Ok(())
+}
@@ -0,0 +1,140 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::result;
+use std::time::Duration;
+
+use xx_core::coroutines::{join_all, select_all, try_join_all};
+use xx_pulse::*;
+
+use super::*;
+
+#[asynchronous]
+async fn sleep_for(millis: u64, interrupted: Rc<Cell<usize>>) -> u64 {
+	if sleep(Duration::from_millis(millis)).await.is_err() {
+		interrupted.set(interrupted.get() + 1);
+
+		return 0;
+	}
+
+	millis
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+	Sleep,
+	Fail,
+	Succeed
+}
+
+#[asynchronous]
+async fn step(step: Step, log: Rc<Cell<(bool, bool)>>) -> result::Result<u32, u32> {
+	match step {
+		Step::Sleep => {
+			sleep(Duration::from_secs(60)).await.unwrap_err();
+
+			log.set((true, log.get().1));
+
+			Err(0)
+		}
+
+		Step::Fail => Err(1),
+
+		Step::Succeed => {
+			log.set((log.get().0, true));
+
+			Ok(2)
+		}
+	}
+}
+
+#[asynchronous]
+async fn maybe_panic(panic: bool) -> u32 {
+	sleep(Duration::from_millis(10)).await.unwrap();
+
+	assert!(!panic, "Task panicked");
+
+	1
+}
+
+#[asynchronous]
+async fn select_none() {
+	let env = internal_get_pulse_env().await;
+
+	unsafe { select_all(env, (0..0).map(|_| maybe_panic(false))).await };
+}
+
+#[asynchronous]
+async fn join_panicking() -> Vec<u32> {
+	let env = internal_get_pulse_env().await;
+
+	unsafe { join_all(env, [false, true, false].map(maybe_panic)).await }
+}
+
+#[asynchronous]
+async fn select_panicking() -> (usize, u32) {
+	let env = internal_get_pulse_env().await;
+
+	unsafe { select_all(env, [true, true].map(maybe_panic)).await }
+}
+
+#[main]
+#[test]
+async fn test_join_all_order() {
+	let env = internal_get_pulse_env().await;
+	let interrupted = Rc::new(Cell::new(0));
+	let tasks = [30, 10, 20].map(|millis| sleep_for(millis, interrupted.clone()));
+
+	let outputs = unsafe { join_all(env, tasks).await };
+
+	assert_eq!(outputs, [30, 10, 20]);
+	assert_eq!(interrupted.get(), 0);
+}
+
+#[main]
+#[test]
+async fn test_select_all() {
+	let env = internal_get_pulse_env().await;
+	let interrupted = Rc::new(Cell::new(0));
+	let tasks = [30, 10, 20].map(|millis| sleep_for(millis, interrupted.clone()));
+
+	let (index, output) = unsafe { select_all(env, tasks).await };
+
+	assert_eq!((index, output), (1, 10));
+
+	/* the rest were cancelled */
+	assert_eq!(interrupted.get(), 2);
+}
+
+#[main]
+#[test]
+async fn test_try_join_all() {
+	let env = internal_get_pulse_env().await;
+	let log = Rc::new(Cell::new((false, false)));
+	let tasks = [Step::Sleep, Step::Fail, Step::Succeed].map(|kind| step(kind, log.clone()));
+
+	let result = unsafe { try_join_all(env, tasks).await };
+
+	/* the first error is returned, the sleeping task was cancelled, and the
+	 * task after the failure never started */
+	assert_eq!(result, Err(1));
+	assert_eq!(log.get(), (true, false));
+
+	let tasks = [Step::Succeed, Step::Succeed].map(|kind| step(kind, log.clone()));
+
+	assert_eq!(unsafe { try_join_all(env, tasks).await }, Ok(vec![2, 2]));
+}
+
+#[main]
+#[test]
+async fn test_select_all_empty() {
+	let result = spawn(select_none()).await.try_join().await;
+
+	assert!(result.is_err());
+}
+
+#[main]
+#[test]
+async fn test_branch_all_panic() {
+	assert!(spawn(join_panicking()).await.try_join().await.is_err());
+	assert!(spawn(select_panicking()).await.try_join().await.is_err());
+}
@@ -0,0 +1,66 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use xx_core::async_std::sync::mpsc::{self, Sender};
+use xx_core::coroutines::{consume_budget, current_budget, yield_now, DEFAULT_BUDGET};
+use xx_pulse::*;
+
+use super::*;
+
+const VALUES: u32 = 1024;
+
+#[asynchronous]
+async fn produce(tx: Sender<u32>) {
+	for i in 0..VALUES {
+		tx.send(i).await.unwrap();
+	}
+}
+
+#[asynchronous]
+async fn set_after_sleep(flag: Rc<Cell<bool>>) {
+	sleep(Duration::from_millis(1)).await.unwrap();
+
+	flag.set(true);
+}
+
+#[main]
+#[test]
+async fn test_budget() {
+	for _ in 0..DEFAULT_BUDGET * 4 {
+		consume_budget().await;
+	}
+
+	assert!(current_budget().await > 0);
+
+	yield_now().await;
+
+	assert_eq!(current_budget().await, DEFAULT_BUDGET);
+
+	let (tx, rx) = mpsc::bounded(4);
+	let producer = spawn(produce(tx)).await;
+
+	for i in 0..VALUES {
+		assert_eq!(rx.recv().await.unwrap(), i);
+	}
+
+	producer.await;
+}
+
+#[main]
+#[test]
+async fn test_budget_fairness() {
+	let flag = Rc::new(Cell::new(false));
+	let sibling = spawn(set_after_sleep(flag.clone())).await;
+	let start = Instant::now();
+
+	/* never suspends by itself, so the sibling only runs if running out of
+	 * budget actually reschedules this task */
+	while !flag.get() && start.elapsed() < Duration::from_secs(5) {
+		consume_budget().await;
+	}
+
+	assert!(flag.get());
+
+	sibling.await;
+}
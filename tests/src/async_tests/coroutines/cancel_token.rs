@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use xx_core::coroutines::{is_interrupted, CancellationToken};
+use xx_pulse::*;
+
+use super::*;
+
+#[asynchronous]
+async fn sleep_bound(token: CancellationToken) -> Result<()> {
+	token.bind(sleep(Duration::from_secs(60))).await
+}
+
+#[asynchronous]
+async fn interrupted_bound(token: CancellationToken) -> bool {
+	token.bind(is_interrupted()).await
+}
+
+#[asynchronous]
+async fn interrupted_after_bind(token: CancellationToken) -> bool {
+	let _ = token.bind(sleep(Duration::from_secs(60))).await;
+
+	is_interrupted().await
+}
+
+#[asynchronous]
+async fn wait_cancelled(token: CancellationToken) -> Result<()> {
+	token.cancelled().await
+}
+
+#[main]
+#[test]
+async fn test_cancellation_token() {
+	let token = CancellationToken::new();
+	let child = token.child_token();
+	let grandchild = child.child_token();
+
+	let sleeping = spawn(sleep_bound(grandchild.clone())).await;
+	let waiting = spawn(wait_cancelled(child.clone())).await;
+
+	assert!(!sleeping.is_done());
+	assert!(!waiting.is_done());
+
+	token.cancel();
+
+	assert!(child.is_cancelled());
+	assert!(grandchild.is_cancelled());
+
+	sleeping.await.unwrap_err();
+	waiting.await.unwrap();
+
+	assert!(spawn(interrupted_bound(grandchild.clone())).await.await);
+
+	/* the interrupt caused by the token ends with the binding */
+	assert!(
+		!spawn(interrupted_after_bind(grandchild.clone()))
+			.await
+			.await
+	);
+	assert!(child.child_token().is_cancelled());
+
+	let token = CancellationToken::new();
+	let child = token.child_token();
+
+	child.cancel();
+
+	assert!(!token.is_cancelled());
+	assert!(!spawn(interrupted_bound(token.clone())).await.await);
+}
@@ -0,0 +1,126 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use xx_core::async_std::AsyncIterator;
+use xx_core::coroutines::TaskGroup;
+use xx_pulse::*;
+
+use super::*;
+
+#[asynchronous]
+async fn sleep_for(millis: u64) -> u64 {
+	sleep(Duration::from_millis(millis)).await.unwrap();
+
+	millis
+}
+
+#[asynchronous]
+async fn count_interrupt(interrupted: Rc<Cell<usize>>) -> u64 {
+	sleep(Duration::from_secs(60)).await.unwrap_err();
+
+	interrupted.set(interrupted.get() + 1);
+
+	0
+}
+
+#[asynchronous]
+async fn panics() -> u64 {
+	panic!("Task panicked");
+}
+
+#[asynchronous]
+async fn drop_group(interrupted: Rc<Cell<usize>>) {
+	let env = internal_get_pulse_env().await;
+	let mut group = TaskGroup::new();
+
+	for _ in 0..3 {
+		unsafe { group.spawn(env, count_interrupt(interrupted.clone())) };
+	}
+}
+
+#[asynchronous]
+async fn join_panic() -> Result<Option<u64>> {
+	let env = internal_get_pulse_env().await;
+	let mut group = TaskGroup::new();
+
+	unsafe { group.spawn(env, panics()) };
+
+	group.join_next().await
+}
+
+#[main]
+#[test]
+async fn test_group_completion_order() {
+	let env = internal_get_pulse_env().await;
+	let mut group = TaskGroup::new();
+
+	for millis in [30, 10, 20] {
+		unsafe { group.spawn(env, sleep_for(millis)) };
+	}
+
+	assert_eq!(group.len(), 3);
+
+	let mut order = Vec::new();
+
+	while let Some(millis) = group.join_next().await.unwrap() {
+		order.push(millis);
+	}
+
+	assert_eq!(order, [10, 20, 30]);
+	assert!(group.is_empty());
+}
+
+#[main]
+#[test]
+async fn test_group_cancel_all() {
+	let env = internal_get_pulse_env().await;
+	let interrupted = Rc::new(Cell::new(0));
+	let mut group = TaskGroup::new();
+
+	for _ in 0..3 {
+		unsafe { group.spawn(env, count_interrupt(interrupted.clone())) };
+	}
+
+	group.cancel_all();
+
+	while let Some(value) = group.join_next().await.unwrap() {
+		assert_eq!(value, 0);
+	}
+
+	assert_eq!(interrupted.get(), 3);
+}
+
+#[main]
+#[test]
+async fn test_group_cancel_on_drop() {
+	let interrupted = Rc::new(Cell::new(0));
+
+	drop_group(interrupted.clone()).await;
+
+	/* the tasks were cancelled and waited for when the group was dropped */
+	assert_eq!(interrupted.get(), 3);
+}
+
+#[main]
+#[test]
+async fn test_group_join_panic() {
+	let result = spawn(join_panic()).await.try_join().await;
+
+	assert!(result.is_err());
+}
+
+#[main]
+#[test]
+async fn test_group_iterator() {
+	let env = internal_get_pulse_env().await;
+	let mut group = TaskGroup::new();
+
+	for millis in [20, 10] {
+		unsafe { group.spawn(env, sleep_for(millis)) };
+	}
+
+	assert_eq!(group.next().await.unwrap().unwrap(), 10);
+	assert_eq!(group.next().await.unwrap().unwrap(), 20);
+	assert!(group.next().await.is_none());
+}
@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use xx_core::coroutines::{interrupt_guard, is_interrupted, take_interrupt};
+use xx_core::coroutines::{
+	check_interrupt, interrupt_guard, is_interrupted, take_interrupt, InterruptKind,
+	InterruptReason
+};
 use xx_pulse::*;
 
 #[asynchronous]
@@ -45,6 +48,38 @@ async fn interruptible() {
 	sleep(Duration::from_secs(1)).await.unwrap();
 }
 
+#[asynchronous]
+async fn interrupt_reason() -> Option<InterruptReason> {
+	sleep(Duration::from_secs(1)).await.unwrap_err();
+
+	let err = check_interrupt().await.unwrap_err();
+
+	assert!(err.is_interrupted());
+	assert_eq!(
+		err.downcast_ref::<InterruptReason>(),
+		Some(&InterruptKind::Timeout.into())
+	);
+
+	let reason = take_interrupt().await;
+
+	assert!(!is_interrupted().await);
+	assert!(take_interrupt().await.is_none());
+
+	reason
+}
+
+#[asynchronous]
+async fn interrupt_no_reason() -> Option<InterruptReason> {
+	sleep(Duration::from_secs(1)).await.unwrap_err();
+
+	let err = check_interrupt().await.unwrap_err();
+
+	assert!(err.is_interrupted());
+	assert!(err.downcast_ref::<InterruptReason>().is_none());
+
+	take_interrupt().await
+}
+
 #[main]
 #[test]
 async fn test_interrupt() {
@@ -58,3 +93,29 @@ async fn test_interrupt() {
 
 	task.await;
 }
+
+#[main]
+#[test]
+async fn test_interrupt_reason() {
+	let task = spawn(interrupt_reason()).await;
+
+	task.request_cancel_with(Some(InterruptKind::Timeout.into()))
+		.unwrap();
+
+	assert_eq!(task.await, Some(InterruptKind::Timeout.into()));
+
+	let task = spawn(interrupt_no_reason()).await;
+
+	task.request_cancel().unwrap();
+
+	assert_eq!(task.await, Some(InterruptReason::default()));
+
+	let reason = InterruptReason::with_message(InterruptKind::Peer, "connection reset");
+
+	assert_eq!(reason.kind(), InterruptKind::Peer);
+	assert_eq!(reason.message(), Some("connection reset"));
+	assert_eq!(
+		reason.to_string(),
+		"Interrupted (cancelled by peer): connection reset"
+	);
+}
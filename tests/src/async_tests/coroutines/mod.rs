@@ -1,6 +1,14 @@
 use super::*;
 
+mod branch_all;
+mod budget;
+mod cancel_token;
 mod concurrency;
+mod group;
 mod interrupt;
 mod join_panic;
+mod registry;
+mod scope;
+mod task_local;
+mod waker;
 mod works;
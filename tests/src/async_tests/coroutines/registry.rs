@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use xx_core::coroutines::{current_task, dump_tasks, TaskId};
+use xx_pulse::*;
+
+#[asynchronous]
+async fn task_id() -> TaskId {
+	current_task().await.id()
+}
+
+#[asynchronous]
+async fn sleeper() {
+	let _ = sleep(Duration::from_secs(5)).await;
+}
+
+fn live_tasks(dump: &str) -> usize {
+	dump.split(' ').next().unwrap().parse().unwrap()
+}
+
+#[main]
+#[test]
+async fn test_task_registry() {
+	let parent = task_id().await;
+	let first = spawn(task_id()).await.await;
+	let second = spawn(task_id()).await.await;
+
+	assert!(parent < first);
+	assert!(first < second);
+
+	let live = live_tasks(&dump_tasks().await);
+	let task = spawn(sleeper()).await;
+	let dump = dump_tasks().await;
+
+	assert_eq!(live_tasks(&dump), live + 1);
+	assert!(dump.contains("blocked on"));
+
+	task.cancel().await;
+
+	assert_eq!(live_tasks(&dump_tasks().await), live);
+}
@@ -0,0 +1,119 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use xx_core::coroutines::scope;
+use xx_pulse::*;
+
+use super::*;
+
+#[asynchronous]
+async fn set_after(count: &mut u64, value: u64) {
+	sleep(Duration::from_millis(value * 10)).await.unwrap();
+
+	*count = value;
+}
+
+#[asynchronous]
+async fn count_interrupt(interrupted: &Cell<usize>) {
+	sleep(Duration::from_secs(60)).await.unwrap_err();
+
+	interrupted.set(interrupted.get() + 1);
+}
+
+#[asynchronous]
+async fn nothing() {}
+
+#[asynchronous]
+async fn panicking_scope(interrupted: Rc<Cell<usize>>) {
+	let env = internal_get_pulse_env().await;
+	let interrupted = &*interrupted;
+
+	scope(env, |scope| async move {
+		for _ in 0..2 {
+			scope.spawn(count_interrupt(interrupted));
+		}
+
+		panic!("Scope body panicked");
+	})
+	.await;
+}
+
+#[asynchronous]
+async fn interrupted_scope(interrupted: Rc<Cell<usize>>) {
+	let env = internal_get_pulse_env().await;
+	let interrupted = &*interrupted;
+
+	scope(env, |scope| async move {
+		for _ in 0..2 {
+			scope.spawn(count_interrupt(interrupted));
+		}
+
+		sleep(Duration::from_secs(60)).await.unwrap_err();
+	})
+	.await;
+}
+
+#[asynchronous]
+async fn spawn_after_end() {
+	let env = internal_get_pulse_env().await;
+	let ended = scope(env, |scope| async move { scope }).await;
+
+	ended.spawn(nothing());
+}
+
+#[main]
+#[test]
+async fn test_scope_borrow() {
+	let env = internal_get_pulse_env().await;
+	let mut counts = [0; 4];
+	let counts_ref = &mut counts;
+
+	let spawned = scope(env, |scope| async move {
+		for (count, value) in counts_ref.iter_mut().zip(1..) {
+			scope.spawn(set_after(count, value));
+		}
+
+		4
+	})
+	.await;
+
+	assert_eq!(spawned, 4);
+	assert_eq!(counts, [1, 2, 3, 4]);
+}
+
+#[main]
+#[test]
+async fn test_scope_panic() {
+	let interrupted = Rc::new(Cell::new(0));
+	let result = spawn(panicking_scope(interrupted.clone()))
+		.await
+		.try_join()
+		.await;
+
+	/* the children were interrupted and joined before the panic resumed */
+	assert!(result.is_err());
+	assert_eq!(interrupted.get(), 2);
+}
+
+#[main]
+#[test]
+async fn test_scope_interrupted() {
+	let interrupted = Rc::new(Cell::new(0));
+	let handle = spawn(interrupted_scope(interrupted.clone())).await;
+
+	assert!(!handle.is_done());
+
+	handle.request_cancel().unwrap();
+	handle.await;
+
+	assert_eq!(interrupted.get(), 2);
+}
+
+#[main]
+#[test]
+async fn test_scope_spawn_after_end() {
+	let result = spawn(spawn_after_end()).await.try_join().await;
+
+	assert!(result.is_err());
+}
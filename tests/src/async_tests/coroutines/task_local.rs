@@ -0,0 +1,55 @@
+use xx_core::coroutines::task_local;
+use xx_pulse::*;
+
+task_local! {
+	static REQUEST_ID: u64;
+
+	#[inherit]
+	static TRACE: String;
+}
+
+#[asynchronous]
+async fn read_request_id() -> Option<u64> {
+	REQUEST_ID.get().await
+}
+
+#[asynchronous]
+async fn read_trace() -> Option<String> {
+	TRACE.get().await
+}
+
+#[main]
+#[test]
+async fn test_task_local() {
+	assert_eq!(read_request_id().await, None);
+
+	let id = REQUEST_ID.scope(1, read_request_id()).await;
+
+	assert_eq!(id, Some(1));
+
+	let ids = REQUEST_ID
+		.scope(2, async {
+			let inner = REQUEST_ID.scope(3, read_request_id()).await;
+
+			(inner, read_request_id().await)
+		})
+		.await;
+
+	assert_eq!(ids, (Some(3), Some(2)));
+	assert_eq!(read_request_id().await, None);
+
+	let (id, trace) = REQUEST_ID
+		.scope(
+			4,
+			TRACE.scope("trace".to_string(), async {
+				let id = spawn(read_request_id()).await.await;
+				let trace = spawn(read_trace()).await.await;
+
+				(id, trace)
+			})
+		)
+		.await;
+
+	assert_eq!(id, None);
+	assert_eq!(trace.as_deref(), Some("trace"));
+}
@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use xx_core::coroutines::EventFdWaker;
+use xx_core::future::{ReqPtr, Request};
+use xx_core::os::poll::{poll, BorrowedPollFd, PollFlag};
+use xx_core::pointer::*;
+
+unsafe fn complete(_: ReqPtr<()>, arg: Ptr<()>, (): ()) {
+	let count = unsafe { arg.cast::<AtomicUsize>().as_ref() };
+
+	count.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn test_eventfd_waker() {
+	let waker = EventFdWaker::new().unwrap();
+	let count = AtomicUsize::new(0);
+	let request = unsafe { Request::new(ptr!(&count).cast(), complete) };
+	let requests = [&request; 16].map(|request| ptr!(request).addr());
+
+	thread::scope(|scope| {
+		for chunk in requests.chunks(4) {
+			let waker = &waker;
+
+			scope.spawn(move || {
+				let handle = unsafe { waker.waker() };
+
+				for request in chunk {
+					unsafe { handle.wake(Ptr::from_addr(*request)) };
+				}
+			});
+		}
+	});
+
+	let mut fds = [BorrowedPollFd::new(waker.fd(), PollFlag::In.into())];
+
+	assert_eq!(poll(&mut fds, Duration::from_secs(1)).unwrap(), 1);
+	assert_eq!(unsafe { waker.run_pending() }, 16);
+	assert_eq!(count.load(Ordering::Relaxed), 16);
+	assert_eq!(poll(&mut fds, Duration::ZERO).unwrap(), 0);
+}
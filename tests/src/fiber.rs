@@ -1,4 +1,8 @@
-use xx_core::fiber::{Fiber, Start};
+use std::mem::replace;
+use std::process::Command;
+use std::{env, thread};
+
+use xx_core::fiber::*;
 use xx_core::pointer::*;
 
 unsafe extern "C" fn start(arg: Ptr<()>) {
@@ -40,3 +44,242 @@ fn test_fibers() {
 		assert_eq!(data.as_mut().2, 10);
 	}
 }
+
+#[inline(never)]
+fn recurse(depth: usize) -> usize {
+	let buf = std::hint::black_box([depth as u8; 1024]);
+
+	if depth == 0 {
+		buf[0] as usize
+	} else {
+		recurse(depth - 1) + buf[1023] as usize
+	}
+}
+
+unsafe extern "C" fn grow(arg: Ptr<()>) {
+	let data = arg.cast::<(Fiber, Fiber, usize)>().cast_mut();
+
+	loop {
+		/* uses about 256 KiB of stack, well past the initial 16 KiB */
+		data.as_mut().2 = recurse(256);
+
+		Fiber::switch(ptr!(&mut data=>1), ptr!(&mut data=>0));
+	}
+}
+
+#[test]
+fn test_growable_fiber() {
+	unsafe {
+		let mut data = (
+			Fiber::main(),
+			Fiber::new_growable(0x4000, 0x100_0000),
+			0usize
+		);
+
+		let data = ptr!(&mut data);
+
+		assert!(data.as_ref().1.is_growable());
+
+		data.as_mut()
+			.1
+			.set_start(Start::new(grow, data.cast_const().cast()));
+
+		for _ in 0..2 {
+			data.as_mut().2 = 0;
+
+			Fiber::switch(ptr!(&mut data=>0), ptr!(&mut data=>1));
+
+			assert_eq!(data.as_ref().2, (0..=256).map(|i| i % 256).sum());
+
+			/* shrinks the stack back down, which must grow again on the next run */
+			data.as_mut().1.clear_stack();
+		}
+	}
+}
+
+#[test]
+fn test_fiber_builder() {
+	let fiber = FiberBuilder::new()
+		.stack_size(0x8000)
+		.guard_pages(2)
+		.no_reserve(true)
+		.build()
+		.unwrap();
+
+	assert_eq!(fiber.stack_size(), 0x8000);
+	assert!(!fiber.is_growable());
+
+	assert_eq!(StackHint::Default.class(), None);
+	assert_eq!(StackHint::Small.class(), Some(SMALL_STACK_SIZE));
+	assert_eq!(StackHint::Size(1).class(), Some(MIN_STACK_SIZE));
+	assert_eq!(StackHint::Size(100_000).class(), Some(0x20000));
+}
+
+#[test]
+fn test_pool_buckets() {
+	let pool = Pool::new();
+	let start = unsafe { Start::new(start, Ptr::null()) };
+
+	let small = pool.new_fiber_with_hint(start, StackHint::Small);
+	let large = pool.new_fiber_with_hint(start, StackHint::Large);
+
+	assert_eq!(small.stack_size(), SMALL_STACK_SIZE);
+	assert_eq!(large.stack_size(), LARGE_STACK_SIZE);
+
+	unsafe {
+		pool.exit_fiber(small);
+		pool.exit_fiber(large);
+	}
+
+	let small = pool.new_fiber_with_hint(start, StackHint::Small);
+	let large = pool.new_fiber_with_hint(start, StackHint::Size(LARGE_STACK_SIZE - 1));
+
+	assert_eq!(small.stack_size(), SMALL_STACK_SIZE);
+	assert_eq!(large.stack_size(), LARGE_STACK_SIZE);
+}
+
+#[test]
+fn test_overflow_handler() {
+	install_overflow_handler();
+	install_overflow_handler();
+
+	/* faults within the growable stack are still handled, and fibers run on
+	 * threads with the handler's signal stack */
+	test_growable_fiber();
+	test_fibers();
+}
+
+unsafe extern "C" fn overflow(arg: Ptr<()>) {
+	let data = arg.cast::<(Fiber, Fiber, usize)>().cast_mut();
+
+	/* far more than the fiber's stack */
+	data.as_mut().2 = recurse(256);
+
+	Fiber::switch(ptr!(&mut data=>1), ptr!(&mut data=>0));
+}
+
+const OVERFLOW_CHILD: &str = "XX_TEST_OVERFLOW_CHILD";
+
+#[test]
+fn test_overflow_report() {
+	if env::var_os(OVERFLOW_CHILD).is_some() {
+		install_overflow_handler();
+
+		unsafe {
+			let fiber = FiberBuilder::new().stack_size(0x8000).build().unwrap();
+			let mut data = (Fiber::main(), fiber, 0usize);
+			let data = ptr!(&mut data);
+
+			data.as_mut().1.set_owner(7);
+			data.as_mut()
+				.1
+				.set_start(Start::new(overflow, data.cast_const().cast()));
+
+			Fiber::switch(ptr!(&mut data=>0), ptr!(&mut data=>1));
+		}
+
+		unreachable!("The fiber did not overflow");
+	}
+
+	/* the overflow aborts, so it runs in a child process */
+	let output = Command::new(env::current_exe().unwrap())
+		.args(["fiber::test_overflow_report", "--exact", "--nocapture"])
+		.env(OVERFLOW_CHILD, "1")
+		.output()
+		.unwrap();
+
+	let stderr = String::from_utf8_lossy(&output.stderr);
+
+	assert!(!output.status.success());
+	assert!(stderr.contains("Fiber overflowed its stack"), "{}", stderr);
+	assert!(stderr.contains(">> Task: #7"), "{}", stderr);
+	assert!(stderr.contains(">> Stack: "), "{}", stderr);
+}
+
+#[test]
+fn test_pool_stats() {
+	let pool = Pool::new().policy(PoolPolicy::new().paint_stacks(true).idle(1));
+
+	unsafe {
+		let mut data = (Fiber::main(), Fiber::main(), 0usize);
+		let data = ptr!(&mut data);
+		let start = Start::new(grow, data.cast_const().cast());
+
+		data.as_mut().1 = pool.new_fiber_with_hint(start, StackHint::Size(0x100000));
+
+		Fiber::switch(ptr!(&mut data=>0), ptr!(&mut data=>1));
+
+		let fiber = replace(&mut data.as_mut().1, Fiber::main());
+
+		assert!(fiber.stack_high_water() >= 256 * 1024);
+
+		pool.exit_fiber(fiber);
+	}
+
+	pool.prewarm(2, StackHint::Small).unwrap();
+
+	let stats = pool.stats();
+
+	assert_eq!(stats.created, 3);
+	assert_eq!(stats.reused, 0);
+	assert_eq!(stats.active, 0);
+	assert_eq!(stats.peak_active, 1);
+	assert_eq!(stats.pooled, 3);
+	assert_eq!(stats.high_water[6..].iter().sum::<u64>(), 1);
+
+	/* the largest stack is freed first */
+	assert_eq!(pool.trim(), 2);
+
+	let stats = pool.stats();
+
+	assert_eq!(stats.pooled, 1);
+	assert_eq!(stats.dropped, 2);
+
+	let fiber =
+		pool.new_fiber_with_hint(unsafe { Start::new(start, Ptr::null()) }, StackHint::Small);
+
+	assert_eq!(fiber.stack_size(), SMALL_STACK_SIZE);
+	assert_eq!(pool.stats().reused, 1);
+}
+
+#[test]
+fn test_local_pool() {
+	let pool = Pool::new();
+	let start = unsafe { Start::new(start, Ptr::null()) };
+	let local = unsafe { LocalPool::new(ptr!(&pool)) };
+
+	let fiber = local.new_fiber_with_hint(start, StackHint::Small);
+
+	unsafe { local.exit_fiber(fiber) };
+
+	let fiber = local.new_fiber_with_hint(start, StackHint::Small);
+
+	assert_eq!(pool.stats().reused, 1);
+
+	/* exiting on another thread returns the fiber to the local pool it came
+	 * from */
+	thread::scope(|scope| {
+		scope.spawn(|| {
+			let other = unsafe { LocalPool::new(ptr!(&pool)) };
+
+			unsafe { other.exit_fiber(fiber) };
+		});
+	});
+
+	assert_eq!(pool.stats().pooled, 1);
+
+	let fiber = local.new_fiber_with_hint(start, StackHint::Small);
+	let stats = pool.stats();
+
+	assert_eq!(stats.reused, 2);
+	assert_eq!(stats.created, 1);
+
+	unsafe { local.exit_fiber(fiber) };
+
+	/* cached fibers move to the shared pool */
+	drop(local);
+
+	let _ = pool.new_fiber_with_hint(start, StackHint::Small);
+
+	assert_eq!(pool.stats().reused, 3);
+}
@@ -1,12 +1,18 @@
 use std::mem::transmute;
 use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::unix::thread::RawPthread;
 use std::time::Duration;
 
 use xx_core::os::error::{result_from_int, result_from_ptr, OsError};
-use xx_core::os::mman::{Advice, Flag, Flags, Map, Protection, Type};
+use xx_core::os::fcntl::Seal;
+use xx_core::os::io_uring::{io_uring_detect_features, Builder, IoRingFeatures, SetupFlag};
+use xx_core::os::mman::{Advice, Flag, Flags, Map, Protection, SharedMemory, Type};
 use xx_core::os::poll::{poll_timeout, PollFd, PollFlag};
 use xx_core::os::resource::{get_rlimit, Resource};
-use xx_core::os::time::{nanotime, ClockId};
+use xx_core::os::sched::{get_affinity, set_affinity, CpuSet};
+use xx_core::os::signal::{pthread_set_sigmask, pthread_signal, Signal, SignalHow};
+use xx_core::os::signalfd::{signal_bit, CreateFlag, SignalFd};
+use xx_core::os::time::*;
 use xx_core::os::unistd::close;
 use xx_core::pointer::{MutPtr, Ptr};
 
@@ -23,6 +29,47 @@ fn test_time() {
 	assert!(nanotime(ClockId::Monotonic).unwrap() > 0);
 }
 
+#[test]
+fn test_timerfd() {
+	let timer = TimerFd::new(ClockId::BootTime, TimerFdFlag::CloseOnExec.into()).unwrap();
+
+	assert_eq!(timer.get().unwrap().value.as_nanos(), 0);
+
+	timer.set(Duration::from_millis(1), Duration::ZERO).unwrap();
+
+	assert_eq!(timer.read().unwrap(), 1);
+
+	timer
+		.set(Duration::from_secs(10), Duration::from_secs(1))
+		.unwrap();
+
+	let spec = timer.get().unwrap();
+
+	assert_eq!(spec.interval.as_nanos(), 1_000_000_000);
+	assert!(spec.value.as_nanos() > 0);
+
+	timer.disarm().unwrap();
+
+	assert_eq!(timer.get().unwrap().value.as_nanos(), 0);
+}
+
+#[test]
+fn test_clock() {
+	assert!(clock_getres(ClockId::Tai).unwrap().as_nanos() > 0);
+
+	let start = nanotime(ClockId::Monotonic).unwrap();
+
+	clock_nanosleep(
+		ClockId::Monotonic,
+		SleepFlag::Absolute.into(),
+		&TimeSpec::from_nanos(start + 1_000_000),
+		None
+	)
+	.unwrap();
+
+	assert!(nanotime(ClockId::Monotonic).unwrap() >= start + 1_000_000);
+}
+
 #[test]
 fn test_rlimit() {
 	assert!(get_rlimit(Resource::Stack).unwrap().current > 0);
@@ -66,6 +113,29 @@ fn test_mmap() {
 	drop(mem);
 }
 
+#[test]
+fn test_shared_memory() {
+	let memory = SharedMemory::new(c"test", 4096).unwrap();
+	let map = memory.map(Protection::Read | Protection::Write).unwrap();
+
+	assert_eq!(memory.size().unwrap(), 4096);
+
+	unsafe { map.as_ptr().cast::<u8>().write_bytes(7, 16) };
+
+	map.flush().unwrap();
+	map.flush_range(8, 8).unwrap();
+
+	let other = SharedMemory::from_fd(memory.fd().try_clone_to_owned().unwrap());
+	let other_map = other.map(Protection::Read.into()).unwrap();
+
+	assert_eq!(unsafe { other_map.as_ptr().cast::<u8>().add(15).read() }, 7);
+
+	memory.add_seals(Seal::Shrink | Seal::Grow).unwrap();
+
+	assert!(other.seals().unwrap().contains(Seal::Shrink | Seal::Grow));
+	assert_eq!(memory.set_size(0).unwrap_err(), OsError::Perm);
+}
+
 #[test]
 fn test_error() {
 	result_from_int(-2).unwrap_err();
@@ -80,3 +150,78 @@ fn test_error() {
 	result_from_ptr(isize::MAX).unwrap();
 	assert_eq!(OsError::from(2), OsError::NoEnt);
 }
+
+#[test]
+fn test_affinity() {
+	let set = get_affinity(None).unwrap();
+
+	assert!(set.count() > 0);
+
+	let cpu = set.iter().next().unwrap();
+
+	set_affinity(None, &CpuSet::single(cpu)).unwrap();
+
+	let pinned = get_affinity(None).unwrap();
+
+	assert_eq!(pinned.count(), 1);
+	assert!(pinned.is_set(cpu));
+
+	set_affinity(None, &set).unwrap();
+}
+
+#[test]
+fn test_io_uring_builder() {
+	let builder = Builder::new(8)
+		.coop_taskrun()
+		.taskrun_flag()
+		.single_issuer();
+	let flags = SetupFlag::CoopTaskrun | SetupFlag::TaskrunFlag | SetupFlag::SingleIssuer;
+
+	assert_eq!(builder.flags(), flags);
+
+	let old = IoRingFeatures {
+		min_ver: 519,
+		setup_flags: SetupFlag::CoopTaskrun | SetupFlag::TaskrunFlag,
+		..Default::default()
+	};
+
+	assert_eq!(builder.unsupported(&old), SetupFlag::SingleIssuer);
+
+	let features = io_uring_detect_features().unwrap().unwrap();
+
+	if !builder.unsupported(&features).is_empty() {
+		return;
+	}
+
+	let (_fd, params) = builder.setup().unwrap();
+
+	assert_eq!(params.sq_entries, 8);
+	assert_eq!(params.cq_entries, 16);
+	assert_eq!(params.flags(), flags);
+	assert_eq!(params.features(), features.features);
+	assert!(params.sq_off.array != 0);
+}
+
+extern "C" {
+	fn pthread_self() -> RawPthread;
+}
+
+#[test]
+fn test_signalfd() {
+	let mask = signal_bit(Signal::User1 as i32);
+	let mut old = [0];
+
+	pthread_set_sigmask(SignalHow::Block, Some(&[mask]), Some(&mut old)).unwrap();
+
+	let fd = SignalFd::new(mask, CreateFlag::NonBlock | CreateFlag::CloseOnExec).unwrap();
+
+	assert_eq!(fd.read().unwrap_err(), OsError::Again);
+
+	pthread_signal(unsafe { pthread_self() }, Signal::User1 as i32).unwrap();
+
+	let info = fd.read().unwrap();
+
+	assert_eq!(info.signal, Signal::User1 as u32);
+
+	pthread_set_sigmask(SignalHow::SetMask, Some(&old), None).unwrap();
+}